@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ctypeparser::ParseOptions;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// The number of each kind of declaration in the synthetic header.
+const SYNTHETIC_TYPES: usize = 2000;
+
+/// Writes `contents` to a header in the temporary directory.
+fn header(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Parses the C library headers, which are large and available everywhere.
+fn libc(c: &mut Criterion) {
+    let path = header(
+        "ctypeparser-bench-libc.h",
+        "#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n#include <time.h>\n",
+    );
+    let options = ParseOptions::new().include_system(true);
+    c.bench_function("parse libc", |b| b.iter(|| options.parse(&path).unwrap()));
+}
+
+/// Parses a header with thousands of small declarations, where the time is
+/// dominated by the per-entity work of the visitor rather than by clang.
+fn synthetic(c: &mut Criterion) {
+    let mut contents = String::new();
+    for i in 0..SYNTHETIC_TYPES {
+        writeln!(
+            contents,
+            "struct s{i} {{ int a; const char *b; struct s{i} *next; \
+             union {{ float f; double d; }} u; int (*callback)(void *, int); }};
+typedef struct s{i} s{i}_t;
+enum e{i} {{ E{i}_A, E{i}_B = 1 << 4, E{i}_C }};
+extern s{i}_t v{i};
+int f{i}(s{i}_t *s, enum e{i} e, ...);",
+            i = i
+        )
+        .unwrap();
+    }
+    let path = header("ctypeparser-bench-synthetic.h", &contents);
+    let options = ParseOptions::new();
+    c.bench_function("parse synthetic", |b| {
+        b.iter(|| options.parse(&path).unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    // Every sample parses a whole header, so fewer are taken than usual.
+    config = Criterion::default().sample_size(10);
+    targets = libc, synthetic
+}
+criterion_main!(benches);
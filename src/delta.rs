@@ -0,0 +1,82 @@
+use crate::Types;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// The difference between the types of a previous run and the current one.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Delta {
+    /// Types that are new or defined differently than before, in the order
+    /// of the current run.
+    pub changed: Vec<Types>,
+    /// The kind and name of types that no longer exist.
+    pub removed: Vec<(&'static str, String)>,
+}
+
+/// Compares `current` to `previous`, e.g. as loaded with `crate::load`, by
+/// the hash of every type's content.
+pub fn delta(previous: &[Types], current: &[Types]) -> Delta {
+    let previous_hashes: HashMap<(&str, &str), u64> = previous
+        .iter()
+        .map(|type_| ((type_.kind(), type_.name()), hash(type_)))
+        .collect();
+
+    let current_keys: HashSet<(&str, &str)> = current
+        .iter()
+        .map(|type_| (type_.kind(), type_.name()))
+        .collect();
+    let removed = previous
+        .iter()
+        .filter(|type_| !current_keys.contains(&(type_.kind(), type_.name())))
+        .map(|type_| (type_.kind(), type_.name().to_string()))
+        .collect();
+
+    let changed = current
+        .iter()
+        .filter(|type_| previous_hashes.get(&(type_.kind(), type_.name())) != Some(&hash(type_)))
+        .cloned()
+        .collect();
+
+    Delta { changed, removed }
+}
+
+fn hash(type_: &Types) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    type_.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{types, variable};
+    use serde_json::json;
+
+    #[test]
+    fn added_changed_and_removed_types() {
+        let previous = types(json!([
+            variable("same", "int"),
+            variable("changed", "int"),
+            variable("removed", "int"),
+        ]));
+        let current = types(json!([
+            variable("same", "int"),
+            variable("changed", "long"),
+            variable("added", "int"),
+        ]));
+        let delta = delta(&previous, &current);
+        assert_eq!(delta.changed, current[1..]);
+        assert_eq!(delta.removed, [("variable", "removed".to_string())]);
+    }
+
+    #[test]
+    fn types_are_keyed_by_kind_and_name() {
+        let previous = types(json!([{"TypeDef": {
+            "name": "same", "location": null, "underlying": "int",
+        }}]));
+        let current = types(json!([variable("same", "int")]));
+        let delta = delta(&previous, &current);
+        assert_eq!(delta.changed, current);
+        assert_eq!(delta.removed, [("typedef", "same".to_string())]);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{layout, Types, UnknownValue};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+mod rust;
+mod table;
+
+/// An output format that parsed types can be emitted in.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Format {
+    Json,
+    Rust,
+    Table,
+    /// The layout of every struct as JSON, including padding.
+    Layout,
+    /// A JSON object mapping the name of every type to its definition.
+    Indexed,
+    /// JSON that is identical for equal sets of types, see `canonical`.
+    Canonical,
+    /// A JSON object mapping the name of every struct to its ABI hash, see
+    /// `layout::abi_hash`.
+    AbiHash,
+}
+
+impl Format {
+    /// Returns the file extension used when writing this format to a file.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Rust => "rs",
+            Format::Table => "txt",
+            Format::Layout => "layout.json",
+            Format::Indexed => "index.json",
+            Format::Canonical => "canonical.json",
+            Format::AbiHash => "abi.json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownValue;
+
+    fn from_str(s: &str) -> Result<Self, UnknownValue> {
+        match s {
+            "json" => Ok(Format::Json),
+            "rust" => Ok(Format::Rust),
+            "table" => Ok(Format::Table),
+            "layout" => Ok(Format::Layout),
+            "indexed" => Ok(Format::Indexed),
+            "canonical" => Ok(Format::Canonical),
+            "abi-hash" => Ok(Format::AbiHash),
+            _ => Err(UnknownValue {
+                option: "format",
+                value: s.to_string(),
+                expected: "one of: json, rust, table, layout, indexed, canonical, abi-hash"
+                    .to_string(),
+            }),
+        }
+    }
+}
+
+/// Renders `types` in the given format.
+pub fn emit(format: Format, types: &[Types]) -> serde_json::Result<String> {
+    match format {
+        Format::Json => Ok(format!("{}\n", serde_json::to_string(types)?)),
+        Format::Rust => Ok(rust::emit(types)),
+        Format::Table => Ok(table::emit(types)),
+        Format::Layout => Ok(format!(
+            "{}\n",
+            serde_json::to_string(&layout::layouts(types))?
+        )),
+        Format::Indexed => Ok(format!("{}\n", serde_json::to_string(&index(types))?)),
+        Format::Canonical => Ok(format!("{}\n", canonical(types)?)),
+        Format::AbiHash => Ok(format!(
+            "{}\n",
+            serde_json::to_string(&layout::abi_hashes(types))?
+        )),
+    }
+}
+
+/// Serializes `types` deterministically, for hashing or caching: object keys
+/// are sorted, there is no whitespace and the types are sorted by kind, then
+/// name, then content, so that the order they were declared in does not
+/// matter.
+pub fn canonical(types: &[Types]) -> serde_json::Result<String> {
+    let mut types = types
+        .iter()
+        .map(|type_| {
+            // Maps of `serde_json::Value` keep their keys sorted.
+            let json = serde_json::to_value(type_)?.to_string();
+            Ok((type_.kind(), type_.name(), json))
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    types.sort();
+
+    let types: Vec<&str> = types.iter().map(|(_, _, json)| json.as_str()).collect();
+    Ok(format!("[{}]", types.join(",")))
+}
+
+/// Checks that the JSON emitted for `types` is read back by `crate::load` as
+/// the same types, and that nothing in it is lost along the way. There is no
+/// separate JSON Schema to validate against: the serde definitions of the
+/// types are the schema of the output, and this round-trip takes the place of
+/// validating against it. A failure means they disagree with each other,
+/// which is a bug in ctypeparser rather than a problem with the header.
+pub fn self_check(types: &[Types]) -> Result<(), SelfCheckError> {
+    let json = serde_json::to_value(types).map_err(SelfCheckError::Json)?;
+    let loaded: Vec<Types> =
+        serde_json::from_value(json.clone()).map_err(SelfCheckError::Schema)?;
+    if loaded != types {
+        return Err(SelfCheckError::Mismatch);
+    }
+    if serde_json::to_value(&loaded).map_err(SelfCheckError::Json)? != json {
+        return Err(SelfCheckError::Lossy);
+    }
+    Ok(())
+}
+
+/// The ways in which `self_check` can fail.
+#[derive(Debug)]
+pub enum SelfCheckError {
+    /// The types could not be serialized.
+    Json(serde_json::Error),
+    /// The emitted JSON could not be loaded.
+    Schema(serde_json::Error),
+    /// The emitted JSON is loaded as different types.
+    Mismatch,
+    /// The emitted JSON contains data that is not loaded.
+    Lossy,
+}
+
+impl fmt::Display for SelfCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelfCheckError::Json(e) => write!(f, "bug: types can't be emitted as JSON: {}", e),
+            SelfCheckError::Schema(e) => {
+                write!(f, "bug: emitted JSON does not match the schema: {}", e)
+            }
+            SelfCheckError::Mismatch => {
+                f.write_str("bug: emitted JSON is not loaded as the types it was emitted for")
+            }
+            SelfCheckError::Lossy => {
+                f.write_str("bug: emitted JSON contains data that is lost when loading it")
+            }
+        }
+    }
+}
+
+impl Error for SelfCheckError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SelfCheckError::Json(e) | SelfCheckError::Schema(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Keys every type by its name. Names shared by several types, such as in
+/// `typedef struct foo foo;`, are prefixed with the kind of each type, e.g.
+/// `struct foo` and `typedef foo`.
+fn index(types: &[Types]) -> BTreeMap<String, &Types> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for type_ in types {
+        *counts.entry(type_.name()).or_default() += 1;
+    }
+
+    let mut index = BTreeMap::new();
+    for (i, type_) in types.iter().enumerate() {
+        let mut key = if counts[type_.name()] > 1 {
+            format!("{} {}", type_.kind(), type_.name())
+        } else {
+            type_.name().to_string()
+        };
+        // A type may still be declared more than once with the same kind,
+        // e.g. a struct that is only forward declared.
+        if index.contains_key(&key) {
+            key = format!("{}#{}", key, i);
+        }
+        index.insert(key, type_);
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{struct_, types, variable};
+    use serde_json::json;
+
+    #[test]
+    fn self_check_accepts_every_kind_of_type() {
+        let types = types(json!([
+            {"TypeDef": {"name": "foo_t", "location": null, "underlying": "struct foo",
+                "type_kind": "Record", "resolved_chain": ["struct foo"]}},
+            {"Struct": {"name": "foo", "location": {"file": "foo.h", "line": 1, "column": 8},
+                "is_definition": true, "size": 8, "alignment": 4, "fields_size": 5,
+                "requested_alignment": null, "fields": [
+                    {"name": "a", "type_": "char", "offset": 0, "size": 1, "alignment": 1,
+                        "requested_alignment": null, "bit_width": null, "bit_offset": null},
+                    {"name": "b", "type_": "int", "offset": 4, "size": 4, "alignment": 4,
+                        "requested_alignment": null, "bit_width": null, "bit_offset": null}]}},
+            {"Enum": {"name": "kind", "location": null, "is_definition": true,
+                "underlying": "unsigned int", "fields": [
+                    {"name": "A", "value": 0, "is_out_of_range": false, "value_hex": "0x0"}]}},
+            {"Union": {"name": "value", "location": null, "is_definition": true, "size": 8,
+                "alignment": 8, "requested_alignment": null, "is_packed": false, "fields": [
+                    {"name": "d", "type_": "double", "size": 8, "requested_alignment": null,
+                        "is_largest": true}]}},
+            {"Variable": {"name": "global", "location": null, "type_": "int [2][3]",
+                "type_kind": "Array", "array_sizes": [2, 3], "array_element": "int"}},
+            {"Function": {"name": "f", "location": null, "return_type": "int",
+                "return_info": {"type_kind": "Primitive", "canonical": "int"},
+                "parameters": [{"name": "x", "type_": "int"}], "is_variadic": false,
+                "has_unspecified_parameters": false}},
+            {"Constant": {"name": "foo::max", "location": null, "type_": "const int",
+                "value": -1}},
+        ]));
+        self_check(&types).unwrap();
+    }
+
+    #[test]
+    fn parse_formats() {
+        assert_eq!("abi-hash".parse::<Format>().unwrap(), Format::AbiHash);
+        assert_eq!(Format::Layout.extension(), "layout.json");
+        let e = "xml".parse::<Format>().unwrap_err();
+        assert_eq!(e.option, "format");
+        assert!(e
+            .to_string()
+            .starts_with("unknown format `xml`, expected one of: json"));
+    }
+
+    #[test]
+    fn canonical_ignores_order() {
+        let a = types(json!([
+            variable("b", "int"),
+            {"TypeDef": {"name": "a", "location": null, "underlying": "int"}},
+            variable("a", "int"),
+        ]));
+        let mut b = a.clone();
+        b.reverse();
+        assert_eq!(canonical(&a).unwrap(), canonical(&b).unwrap());
+
+        let canonical = canonical(&a).unwrap();
+        assert!(!canonical.contains(char::is_whitespace));
+        let names: Vec<String> = serde_json::from_str::<Vec<Types>>(&canonical)
+            .unwrap()
+            .iter()
+            .map(|type_| format!("{} {}", type_.kind(), type_.name()))
+            .collect();
+        assert_eq!(names, ["typedef a", "variable a", "variable b"]);
+    }
+
+    #[test]
+    fn index_disambiguates_shared_names() {
+        let types = types(json!([
+            variable("a", "int"),
+            {"TypeDef": {"name": "a", "location": null, "underlying": "int"}},
+            variable("b", "int"),
+            variable("b", "int"),
+            variable("c", "int"),
+        ]));
+        let keys: Vec<String> = index(&types).into_keys().collect();
+        assert_eq!(
+            keys,
+            ["c", "typedef a", "variable a", "variable b", "variable b#3"]
+        );
+    }
+
+    #[test]
+    fn abi_hashes_are_keyed_by_struct() {
+        let types = types(json!([variable("a", "int"), struct_("s", Some(0), vec![])]));
+        let output = emit(Format::AbiHash, &types).unwrap();
+        let hashes: BTreeMap<String, String> = serde_json::from_str(&output).unwrap();
+        assert_eq!(hashes.keys().collect::<Vec<_>>(), ["s"]);
+    }
+}
@@ -0,0 +1,651 @@
+use crate::layout::{self, Layout};
+use crate::{
+    ConstantType, EnumType, FunctionSignature, FunctionType, StructType, TypeDefType, TypeInfo,
+    TypeKind, Types, UnionType, VariableType,
+};
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "yield",
+];
+
+/// Renders `types` as `#[repr(C)]` Rust declarations.
+pub fn emit(types: &[Types]) -> String {
+    let mut out = String::from("use std::os::raw::*;\n");
+    for type_ in types {
+        out.push('\n');
+        match type_ {
+            Types::TypeDef(t) => emit_typedef(&mut out, t),
+            Types::Struct(s) => emit_struct(&mut out, s),
+            Types::Enum(e) => emit_enum(&mut out, e),
+            Types::Union(u) => emit_union(&mut out, u),
+            Types::Variable(v) => emit_variable(&mut out, v),
+            Types::Function(f) => emit_function(&mut out, f),
+            Types::Constant(c) => emit_constant(&mut out, c),
+        }
+    }
+    out
+}
+
+fn emit_typedef(out: &mut String, typedef: &TypeDefType) {
+    let underlying = field_type(&typedef.underlying, &typedef.info);
+    // `typedef struct foo foo;` needs no alias as structs share a namespace
+    // with types in Rust.
+    if underlying != typedef.name {
+        writeln!(
+            out,
+            "pub type {} = {};",
+            type_ident(&typedef.name),
+            underlying
+        )
+        .unwrap();
+    }
+}
+
+fn emit_struct(out: &mut String, struct_: &StructType) {
+    if struct_.fields.iter().any(|field| field.bit_width.is_some()) {
+        if let Some(layout) = layout::layout(struct_) {
+            return emit_bit_field_struct(out, struct_, &layout);
+        }
+    }
+    emit_repr(
+        out,
+        struct_.is_packed,
+        struct_.alignment,
+        struct_.requested_alignment,
+    );
+    writeln!(out, "pub struct {} {{", type_ident(&struct_.name)).unwrap();
+    for (i, field) in struct_.fields.iter().enumerate() {
+        writeln!(
+            out,
+            "    pub {}: {},",
+            field_ident(field.name.as_deref(), i),
+            field_type(&field.type_, &field.info)
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+/// Emits a struct containing bit-fields, which Rust has no equivalent of. The
+/// storage shared by consecutive bit-fields is emitted as an opaque byte
+/// array, along with the padding and alignment it would otherwise lose.
+fn emit_bit_field_struct(out: &mut String, struct_: &StructType, layout: &Layout) {
+    emit_repr(
+        out,
+        struct_.is_packed,
+        struct_.alignment,
+        struct_.requested_alignment.or(struct_.alignment),
+    );
+    writeln!(out, "pub struct {} {{", type_ident(&struct_.name)).unwrap();
+    // The other entries are the fields that are not bit-fields, in order.
+    let mut fields = struct_
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.bit_width.is_none());
+    for (i, entry) in layout.entries.iter().enumerate() {
+        if entry.is_padding() {
+            writeln!(out, "    __padding{}: [u8; {}],", i, entry.size).unwrap();
+        } else if !entry.bit_fields.is_empty() {
+            let bit_fields: Vec<String> = entry
+                .bit_fields
+                .iter()
+                .map(|bit_field| {
+                    format!(
+                        "`{}`: bits {}..{}",
+                        bit_field.name.as_deref().unwrap_or("_"),
+                        bit_field.bit_offset,
+                        bit_field.bit_offset + bit_field.width
+                    )
+                })
+                .collect();
+            writeln!(out, "    /// {}", bit_fields.join(", ")).unwrap();
+            writeln!(out, "    pub __bitfield{}: [u8; {}],", i, entry.size).unwrap();
+        } else if let Some((j, field)) = fields.next() {
+            writeln!(
+                out,
+                "    pub {}: {},",
+                field_ident(field.name.as_deref(), j),
+                field_type(&field.type_, &field.info)
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("}\n");
+}
+
+fn emit_repr(
+    out: &mut String,
+    is_packed: bool,
+    alignment: Option<usize>,
+    requested_alignment: Option<usize>,
+) {
+    // Rust does not allow a type to be both packed and aligned, so packing,
+    // which the alignment of a packed record is a result of, wins.
+    match (is_packed, alignment, requested_alignment) {
+        (true, Some(alignment), _) => writeln!(out, "#[repr(C, packed({}))]", alignment).unwrap(),
+        (true, None, _) => out.push_str("#[repr(C, packed)]\n"),
+        (false, _, Some(alignment)) => writeln!(out, "#[repr(C, align({}))]", alignment).unwrap(),
+        (false, _, None) => out.push_str("#[repr(C)]\n"),
+    }
+    out.push_str("#[derive(Copy, Clone)]\n");
+}
+
+fn emit_enum(out: &mut String, enum_: &EnumType) {
+    // C enums are plain integers and may hold values that are not one of
+    // their constants, so they can't be safely represented as a Rust enum.
+    let name = type_ident(&enum_.name);
+    let underlying = rust_type(enum_.underlying.as_deref().unwrap_or("int"));
+    writeln!(out, "pub type {} = {};", name, underlying).unwrap();
+    for field in &enum_.fields {
+        // Values of 64-bit unsigned enums that do not fit in an i64 are
+        // stored as negative numbers, which the cast wraps back around.
+        let value = if field.value < 0 && is_unsigned(&underlying) {
+            format!("{}i64 as {}", field.value, name)
+        } else {
+            field.value.to_string()
+        };
+        writeln!(
+            out,
+            "pub const {}: {} = {};",
+            ident(&field.name),
+            name,
+            value
+        )
+        .unwrap();
+    }
+}
+
+/// Returns whether the Rust type `rust`, as returned by `rust_type`, is an
+/// unsigned integer.
+fn is_unsigned(rust: &str) -> bool {
+    rust.starts_with("c_u") || matches!(rust, "u8" | "u16" | "u32" | "u64" | "u128" | "usize")
+}
+
+fn emit_union(out: &mut String, union: &UnionType) {
+    emit_repr(
+        out,
+        union.is_packed,
+        union.alignment,
+        union.requested_alignment,
+    );
+    writeln!(out, "pub union {} {{", type_ident(&union.name)).unwrap();
+    for (i, field) in union.fields.iter().enumerate() {
+        writeln!(
+            out,
+            "    pub {}: {},",
+            field_ident(field.name.as_deref(), i),
+            field_type(&field.type_, &field.info)
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn emit_variable(out: &mut String, variable: &VariableType) {
+    out.push_str("extern \"C\" {\n");
+    writeln!(
+        out,
+        "    pub static mut {}: {};",
+        ident(&variable.name),
+        field_type(&variable.type_, &variable.info)
+    )
+    .unwrap();
+    out.push_str("}\n");
+}
+
+fn emit_function(out: &mut String, function: &FunctionType) {
+    let (parameters, returns) = signature(&function.signature);
+    out.push_str("extern \"C\" {\n");
+    writeln!(
+        out,
+        "    pub fn {}({}){};",
+        ident(&function.name),
+        parameters,
+        returns
+    )
+    .unwrap();
+    out.push_str("}\n");
+}
+
+fn emit_constant(out: &mut String, constant: &ConstantType) {
+    // The type is spelled `const int`, which is meaningless in Rust.
+    let type_ = constant.type_.trim_start_matches("const ");
+    writeln!(
+        out,
+        "pub const {}: {} = {};",
+        type_ident(&constant.name),
+        rust_type(type_),
+        constant.value
+    )
+    .unwrap();
+}
+
+/// Returns the parameter list and return type, if any, of `signature`.
+fn signature(signature: &FunctionSignature) -> (String, String) {
+    let mut parameters: Vec<String> = signature
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, parameter)| {
+            let name = match &parameter.name {
+                Some(name) => ident(name),
+                None => format!("arg{}", i),
+            };
+            format!(
+                "{}: {}",
+                name,
+                field_type(&parameter.type_, &parameter.info)
+            )
+        })
+        .collect();
+    if signature.is_variadic {
+        parameters.push("...".to_string());
+    }
+
+    let info = &signature.return_info;
+    let returns = if info.type_kind == TypeKind::Primitive && info.canonical == "void" {
+        String::new()
+    } else {
+        format!(" -> {}", field_type(&signature.return_type, info))
+    };
+    (parameters.join(", "), returns)
+}
+
+/// Maps the C type of a field, variable or parameter to the equivalent Rust
+/// type, using the signature of function pointers. These are nullable in C,
+/// so they become an `Option` of a Rust function pointer.
+fn field_type(c: &str, info: &TypeInfo) -> String {
+    let function = match &info.function {
+        Some(function) if info.pointer_depth == 1 => {
+            let (parameters, returns) = signature(function);
+            Some(format!(
+                "Option<unsafe extern \"C\" fn({}){}>",
+                parameters, returns
+            ))
+        }
+        _ => None,
+    };
+    match function {
+        // The spelling of an array of function pointers, e.g.
+        // `int (*[4])(int)`, can't be split into its element and lengths.
+        Some(function) if info.type_kind == TypeKind::Array => {
+            // Flexible array members have no size.
+            let sizes: &[usize] = if info.array_sizes.is_empty() {
+                &[0]
+            } else {
+                &info.array_sizes
+            };
+            sizes
+                .iter()
+                .rev()
+                .fold(function, |element, size| format!("[{}; {}]", element, size))
+        }
+        Some(function) => function,
+        None if info.type_kind == TypeKind::Array => {
+            rust_type(&with_array_sizes(c, &info.array_sizes))
+        }
+        None => rust_type(c),
+    }
+}
+
+/// Replaces the lengths in an array type spelled like `char [MAX_LEN]` with
+/// their evaluated values, as the names they are spelled with may not be
+/// usable in Rust.
+fn with_array_sizes(c: &str, sizes: &[usize]) -> String {
+    let mut out = String::new();
+    let mut rest = c;
+    for size in sizes {
+        match (rest.find('['), rest.find(']')) {
+            (Some(start), Some(end)) if start < end => {
+                write!(out, "{}[{}]", &rest[..start], size).unwrap();
+                rest = &rest[end + 1..];
+            }
+            _ => break,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the identifier of the `i`th member of a record. Rust has no
+/// anonymous members, so those are given a name.
+fn field_ident(name: Option<&str>, i: usize) -> String {
+    match name {
+        Some(name) => ident(name),
+        None => format!("__anon{}", i),
+    }
+}
+
+/// Turns the name of a type, which may be qualified like `foo::kind` for
+/// types nested in a record, into a Rust identifier.
+fn type_ident(name: &str) -> String {
+    name.replace("::", "_")
+}
+
+/// Maps a C type as spelled by clang, e.g. `const char *` or `int [4]`, to
+/// the equivalent Rust type.
+fn rust_type(c: &str) -> String {
+    let c = without_atomic(c);
+    let c = c.trim();
+
+    // A function pointer's signature can't be recovered from its spelling,
+    // see `field_type`.
+    if c.contains('(') {
+        return "*mut c_void".to_string();
+    }
+
+    // `int [2][3]` is an array of two `int [3]`.
+    if let (Some(start), Some(end)) = (c.find('['), c.find(']')) {
+        let element = format!("{}{}", &c[..start], &c[end + 1..]);
+        let len = c[start + 1..end].trim();
+        if len.is_empty() {
+            // Flexible array members have no size.
+            return format!("[{}; 0]", rust_type(&element));
+        }
+        return format!("[{}; {}]", rust_type(&element), len);
+    }
+
+    let c = c.strip_suffix("const").unwrap_or(c).trim_end();
+    if let Some(pointee) = c.strip_suffix('*') {
+        let pointee = pointee.trim();
+        let mutability = if pointee.starts_with("const ") {
+            "const"
+        } else {
+            "mut"
+        };
+        return format!("*{} {}", mutability, rust_type(pointee));
+    }
+
+    let base = c
+        .split_whitespace()
+        .filter(|word| !matches!(*word, "const" | "volatile" | "struct" | "union" | "enum"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let base = integer_name(&base).unwrap_or(base);
+    primitive(&base).map_or_else(|| type_ident(&base), str::to_string)
+}
+
+/// Replaces every `_Atomic(T)` in a type spelled like `_Atomic(int) *` with
+/// `T`. Atomic types have the size and alignment of `T` on the platforms Rust
+/// supports, so the layout is unaffected.
+fn without_atomic(c: &str) -> String {
+    let mut out = String::new();
+    let mut rest = c;
+    while let Some(start) = rest.find("_Atomic(") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + "_Atomic(".len()..];
+        // Find the parenthesis closing `_Atomic(`, dropping it.
+        let mut depth = 0;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| match c {
+                '(' => {
+                    depth += 1;
+                    false
+                }
+                ')' if depth == 0 => true,
+                ')' => {
+                    depth -= 1;
+                    false
+                }
+                _ => false,
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        out.push_str(&without_atomic(&rest[..end]));
+        rest = rest.get(end + 1..).unwrap_or_default();
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Returns the name clang uses for an integer type that may be spelled in
+/// several ways, e.g. `long long` for `long long int` or
+/// `signed long long`, and `unsigned long` for `long unsigned int`.
+fn integer_name(c: &str) -> Option<String> {
+    let words: Vec<&str> = c.split_whitespace().collect();
+    let is_integer = words.iter().all(|word| {
+        matches!(
+            *word,
+            "signed" | "unsigned" | "short" | "long" | "int" | "char"
+        )
+    });
+    if words.is_empty() || !is_integer {
+        return None;
+    }
+
+    let has = |word| words.contains(&word);
+    let width = if has("char") {
+        // Unlike other integers, `char` is distinct from `signed char`.
+        if has("signed") {
+            return Some("signed char".to_string());
+        }
+        "char"
+    } else if has("short") {
+        "short"
+    } else {
+        match words.iter().filter(|word| **word == "long").count() {
+            0 => "int",
+            1 => "long",
+            _ => "long long",
+        }
+    };
+    if has("unsigned") {
+        Some(format!("unsigned {}", width))
+    } else {
+        Some(width.to_string())
+    }
+}
+
+fn primitive(c: &str) -> Option<&'static str> {
+    let rust = match c {
+        "void" => "c_void",
+        "_Bool" | "bool" => "bool",
+        "char" => "c_char",
+        "signed char" => "c_schar",
+        "unsigned char" => "c_uchar",
+        "short" => "c_short",
+        "unsigned short" => "c_ushort",
+        "int" => "c_int",
+        "unsigned int" => "c_uint",
+        "long" => "c_long",
+        "unsigned long" => "c_ulong",
+        "long long" => "c_longlong",
+        "unsigned long long" => "c_ulonglong",
+        "__int128" | "__int128_t" => "i128",
+        "unsigned __int128" | "__uint128_t" => "u128",
+        "float" => "c_float",
+        "double" => "c_double",
+        "int8_t" => "i8",
+        "int16_t" => "i16",
+        "int32_t" => "i32",
+        "int64_t" => "i64",
+        "uint8_t" => "u8",
+        "uint16_t" => "u16",
+        "uint32_t" => "u32",
+        "uint64_t" => "u64",
+        "size_t" | "uintptr_t" => "usize",
+        "ssize_t" | "ptrdiff_t" | "intptr_t" => "isize",
+        _ => return None,
+    };
+    Some(rust)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, struct_, types};
+    use serde_json::json;
+
+    #[test]
+    fn rust_types() {
+        assert_eq!(rust_type("int"), "c_int");
+        assert_eq!(rust_type("const char *"), "*const c_char");
+        assert_eq!(rust_type("char *const"), "*mut c_char");
+        assert_eq!(rust_type("struct foo **"), "*mut *mut foo");
+        assert_eq!(rust_type("int [2][3]"), "[[c_int; 3]; 2]");
+        assert_eq!(rust_type("char []"), "[c_char; 0]");
+        assert_eq!(rust_type("foo::kind"), "foo_kind");
+        assert_eq!(rust_type("uint32_t"), "u32");
+        assert_eq!(rust_type("int (*)(int)"), "*mut c_void");
+    }
+
+    #[test]
+    fn records() {
+        let out = emit(&types(json!([
+            {"TypeDef": {"name": "foo", "location": null, "underlying": "foo"}},
+            {"Struct": {"name": "foo", "location": null, "is_definition": true, "size": 5,
+                "alignment": 1, "fields_size": 5, "requested_alignment": null,
+                "is_packed": true, "fields": [
+                    {"name": "type", "type_": "int", "offset": 0, "requested_alignment": null,
+                        "bit_width": null, "bit_offset": null},
+                    {"name": null, "type_": "char", "offset": 4, "requested_alignment": null,
+                        "bit_width": null, "bit_offset": null}]}},
+            {"Union": {"name": "u", "location": null, "is_definition": true, "size": 8,
+                "alignment": 8, "requested_alignment": 8, "is_packed": false, "fields": []}},
+        ])));
+        assert_eq!(
+            out,
+            "use std::os::raw::*;\n\
+             \n\
+             \n\
+             #[repr(C, packed(1))]\n\
+             #[derive(Copy, Clone)]\n\
+             pub struct foo {\n    \
+                 pub r#type: c_int,\n    \
+                 pub __anon1: c_char,\n\
+             }\n\
+             \n\
+             #[repr(C, align(8))]\n\
+             #[derive(Copy, Clone)]\n\
+             pub union u {\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn bit_fields() {
+        let bit_field = |name, bit_offset, width| {
+            let mut field = field(Some(name), "unsigned int", None);
+            field["bit_offset"] = json!(bit_offset);
+            field["bit_width"] = json!(width);
+            field
+        };
+        let mut c = field(Some("c"), "char", Some(1));
+        c["size"] = json!(1);
+        let mut flags = struct_(
+            "flags",
+            Some(4),
+            vec![bit_field("a", 0, 3), bit_field("b", 3, 5), c],
+        );
+        flags["Struct"]["alignment"] = json!(4);
+        assert_eq!(
+            emit(&types(json!([flags]))),
+            "use std::os::raw::*;\n\
+             \n\
+             #[repr(C, align(4))]\n\
+             #[derive(Copy, Clone)]\n\
+             pub struct flags {\n    \
+                 /// `a`: bits 0..3, `b`: bits 3..8\n    \
+                 pub __bitfield0: [u8; 1],\n    \
+                 pub c: c_char,\n    \
+                 __padding2: [u8; 2],\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let out = emit(&types(json!([{"Function": {
+            "name": "f", "location": null, "return_type": "void",
+            "return_info": {"type_kind": "Primitive", "canonical": "void"},
+            "parameters": [{"name": null, "type_": "const char *"}], "is_variadic": true,
+        }}])));
+        assert!(out.contains("    pub fn f(arg0: *const c_char, ...);\n"));
+    }
+
+    #[test]
+    fn unsigned_enums() {
+        let out = emit(&types(json!([{"Enum": {
+            "name": "flags", "location": null, "is_definition": true,
+            "underlying": "unsigned long", "fields": [
+                {"name": "LOW", "value": 1, "is_out_of_range": false, "value_hex": null},
+                {"name": "HIGH", "value": i64::MIN, "is_out_of_range": false,
+                    "value_hex": null}],
+        }}])));
+        assert!(out.contains("pub type flags = c_ulong;\n"));
+        assert!(out.contains("pub const LOW: flags = 1;\n"));
+        assert!(out.contains("pub const HIGH: flags = -9223372036854775808i64 as flags;\n"));
+    }
+
+    #[test]
+    fn function_pointers() {
+        let info: TypeInfo = serde_json::from_value(json!({
+            "type_kind": "Pointer", "pointer_depth": 1,
+            "function": {"return_type": "int", "return_info": {"type_kind": "Primitive",
+                "canonical": "int"}, "parameters": [{"name": "x", "type_": "int"}],
+                "is_variadic": false},
+        }))
+        .unwrap();
+        assert_eq!(
+            field_type("int (*)(int)", &info),
+            "Option<unsafe extern \"C\" fn(x: c_int) -> c_int>"
+        );
+
+        let array = TypeInfo {
+            type_kind: TypeKind::Array,
+            array_sizes: vec![4, 2],
+            ..info
+        };
+        assert_eq!(
+            field_type("int (*[4][2])(int)", &array),
+            "[[Option<unsafe extern \"C\" fn(x: c_int) -> c_int>; 2]; 4]"
+        );
+    }
+
+    #[test]
+    fn integer_names() {
+        assert_eq!(integer_name("long long int").as_deref(), Some("long long"));
+        assert_eq!(
+            integer_name("signed long long").as_deref(),
+            Some("long long")
+        );
+        assert_eq!(
+            integer_name("long unsigned int").as_deref(),
+            Some("unsigned long")
+        );
+        assert_eq!(integer_name("short int").as_deref(), Some("short"));
+        assert_eq!(integer_name("unsigned").as_deref(), Some("unsigned int"));
+        assert_eq!(integer_name("signed char").as_deref(), Some("signed char"));
+        assert_eq!(integer_name("char").as_deref(), Some("char"));
+        assert_eq!(integer_name("float"), None);
+        assert_eq!(integer_name(""), None);
+    }
+
+    #[test]
+    fn atomic_types() {
+        assert_eq!(without_atomic("_Atomic(int)"), "int");
+        assert_eq!(without_atomic("_Atomic(int) *"), "int *");
+        assert_eq!(without_atomic("_Atomic(_Atomic(int) *)"), "int *");
+        assert_eq!(rust_type("_Atomic(unsigned long)"), "c_ulong");
+        assert_eq!(rust_type("_Atomic(int) *"), "*mut c_int");
+    }
+
+    #[test]
+    fn evaluated_array_sizes() {
+        assert_eq!(with_array_sizes("char [MAX_LEN]", &[16]), "char [16]");
+        assert_eq!(with_array_sizes("int [N][N + 1]", &[2, 3]), "int [2][3]");
+    }
+}
@@ -0,0 +1,165 @@
+use crate::Types;
+use std::fmt::Write;
+
+const HEADER: [&str; 5] = ["name", "type", "offset", "size", "align"];
+
+/// Renders the layout of every struct and union as an aligned text table,
+/// for reviewing an ABI at a glance.
+pub fn emit(types: &[Types]) -> String {
+    let mut out = String::new();
+    for type_ in types {
+        let (kind, name, size, alignment, padding, rows) = match type_ {
+            Types::Struct(s) => {
+                let rows = s
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        let info = &f.info;
+                        row(
+                            field_name(&f.name),
+                            &f.type_,
+                            f.offset,
+                            info.size,
+                            info.alignment,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                // Why a struct is bigger than its fields is a commonly asked
+                // question.
+                let padding = match (s.size, s.fields_size) {
+                    (Some(size), Some(fields_size)) => format!(
+                        ", fields {}, padding {}",
+                        fields_size,
+                        size.saturating_sub(fields_size)
+                    ),
+                    _ => String::new(),
+                };
+                ("struct", &s.name, s.size, s.alignment, padding, rows)
+            }
+            Types::Union(u) => {
+                let rows = u
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        let info = &f.info;
+                        row(
+                            field_name(&f.name),
+                            &f.type_,
+                            Some(0),
+                            info.size,
+                            info.alignment,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                ("union", &u.name, u.size, u.alignment, String::new(), rows)
+            }
+            _ => continue,
+        };
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        writeln!(
+            out,
+            "{} {} (size {}, align {}{})",
+            kind,
+            name,
+            number(size),
+            number(alignment),
+            padding
+        )
+        .unwrap();
+        write_table(&mut out, &rows);
+    }
+    out
+}
+
+fn row(
+    name: &str,
+    type_: &str,
+    offset: Option<usize>,
+    size: Option<usize>,
+    alignment: Option<usize>,
+) -> [String; 5] {
+    [
+        name.to_string(),
+        type_.to_string(),
+        number(offset),
+        number(size),
+        number(alignment),
+    ]
+}
+
+fn field_name(name: &Option<String>) -> &str {
+    name.as_deref().unwrap_or("(anonymous)")
+}
+
+/// Formats a layout value, which is unknown for incomplete types.
+fn number(n: Option<usize>) -> String {
+    n.map_or_else(|| "?".to_string(), |n| n.to_string())
+}
+
+fn write_table(out: &mut String, rows: &[[String; 5]]) {
+    let mut widths = HEADER.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let header = HEADER.map(str::to_string);
+    write_row(out, &header, &widths);
+    let separator = widths
+        .iter()
+        .map(|&width| "-".repeat(width))
+        .collect::<Vec<_>>()
+        .join("-+-");
+    writeln!(out, "{}", separator).unwrap();
+    for row in rows {
+        write_row(out, row, &widths);
+    }
+}
+
+fn write_row(out: &mut String, row: &[String; 5], widths: &[usize; 5]) {
+    let cells = row
+        .iter()
+        .zip(widths)
+        .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+        .collect::<Vec<_>>();
+    writeln!(out, "{}", cells.join(" | ").trim_end()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::types;
+    use serde_json::json;
+
+    #[test]
+    fn tables() {
+        let types = types(json!([
+            {"Struct": {"name": "foo", "location": null, "is_definition": true, "size": 8,
+                "alignment": 4, "fields_size": 5, "requested_alignment": null, "fields": [
+                    {"name": "a", "type_": "char", "offset": 0, "size": 1, "alignment": 1,
+                        "requested_alignment": null, "bit_width": null, "bit_offset": null},
+                    {"name": null, "type_": "int", "offset": 4, "size": 4, "alignment": 4,
+                        "requested_alignment": null, "bit_width": null, "bit_offset": null}]}},
+            {"Variable": {"name": "v", "location": null, "type_": "int"}},
+            {"Union": {"name": "bar", "location": null, "is_definition": false, "size": null,
+                "alignment": null, "requested_alignment": null, "is_packed": false,
+                "fields": []}},
+        ]));
+        assert_eq!(
+            emit(&types),
+            "struct foo (size 8, align 4, fields 5, padding 3)\n\
+             name        | type | offset | size | align\n\
+             ------------+------+--------+------+------\n\
+             a           | char | 0      | 1    | 1\n\
+             (anonymous) | int  | 4      | 4    | 4\n\
+             \n\
+             union bar (size ?, align ?)\n\
+             name | type | offset | size | align\n\
+             -----+------+--------+------+------\n"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::Types;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+
+/// Words in type spellings that are not names of types.
+const KEYWORDS: &[&str] = &[
+    "_Atomic",
+    "_Bool",
+    "_Complex",
+    "_Float16",
+    "__float128",
+    "__fp16",
+    "__int128",
+    "__int128_t",
+    "__restrict",
+    "__uint128_t",
+    "bool",
+    "char",
+    "char16_t",
+    "char32_t",
+    "char8_t",
+    "const",
+    "double",
+    "enum",
+    "float",
+    "int",
+    "long",
+    "restrict",
+    "short",
+    "signed",
+    "struct",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "wchar_t",
+];
+
+/// Returns the names of the types that `type_` refers to, e.g. the types of
+/// the fields of a struct.
+pub fn references(type_: &Types) -> BTreeSet<String> {
+    let spellings: Vec<&str> = match type_ {
+        Types::TypeDef(t) => vec![&t.underlying],
+        Types::Struct(s) => s.fields.iter().map(|f| f.type_.as_str()).collect(),
+        Types::Enum(e) => e.underlying.iter().map(String::as_str).collect(),
+        Types::Union(u) => u.fields.iter().map(|f| f.type_.as_str()).collect(),
+        Types::Variable(v) => vec![&v.type_],
+        Types::Function(f) => {
+            let signature = &f.signature;
+            let mut spellings = vec![signature.return_type.as_str()];
+            spellings.extend(signature.parameters.iter().map(|p| p.type_.as_str()));
+            spellings
+        }
+        Types::Constant(c) => vec![&c.type_],
+    };
+
+    spellings
+        .into_iter()
+        .flat_map(identifiers)
+        .filter(|name| !KEYWORDS.contains(name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Splits a type spelling like `const struct foo *[4]` into the identifiers
+/// it contains, keeping qualified names like `foo::kind` whole. So are names
+/// like `::global`, given to the anonymous type of a file-scope variable.
+fn identifiers(spelling: &str) -> impl Iterator<Item = &str> {
+    spelling
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|word| word.trim_end_matches(':'))
+        .filter(|word| {
+            let unqualified = word.strip_prefix("::").unwrap_or(word);
+            unqualified.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        })
+}
+
+/// Returns the names of the types that are referred to by any of `types` but
+/// not defined among them, e.g. types declared in system headers that were
+/// left out, or structs that are only ever forward declared.
+pub fn unresolved(types: &[Types]) -> BTreeSet<String> {
+    let by_name = by_name(types);
+    types
+        .iter()
+        .flat_map(references)
+        .filter(|name| match by_name.get(name.as_str()) {
+            Some(definitions) => definitions.iter().all(|d| !is_defined(d)),
+            None => true,
+        })
+        .collect()
+}
+
+/// A root given to `prune` that is not the name of any type.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownRoot(pub String);
+
+impl fmt::Display for UnknownRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "root `{}` is not declared in the headers", self.0)
+    }
+}
+
+impl Error for UnknownRoot {}
+
+/// Keeps only the types named in `roots` and the types they transitively
+/// refer to, preserving their order. The result is not self-contained if
+/// some of those are not defined, see `unresolved`.
+pub fn prune<S: AsRef<str>>(types: Vec<Types>, roots: &[S]) -> Result<Vec<Types>, UnknownRoot> {
+    let by_name = by_name(&types);
+    for root in roots {
+        if !by_name.contains_key(root.as_ref()) {
+            return Err(UnknownRoot(root.as_ref().to_string()));
+        }
+    }
+
+    let mut reachable: BTreeSet<String> = BTreeSet::new();
+    let mut pending: Vec<String> = roots.iter().map(|r| r.as_ref().to_string()).collect();
+    while let Some(name) = pending.pop() {
+        if !reachable.insert(name.clone()) {
+            continue;
+        }
+        for definition in by_name.get(name.as_str()).into_iter().flatten() {
+            pending.extend(references(definition));
+        }
+    }
+
+    Ok(types
+        .into_iter()
+        .filter(|type_| reachable.contains(type_.name()))
+        .collect())
+}
+
+fn by_name(types: &[Types]) -> HashMap<&str, Vec<&Types>> {
+    let mut by_name: HashMap<&str, Vec<&Types>> = HashMap::new();
+    for type_ in types {
+        by_name.entry(type_.name()).or_default().push(type_);
+    }
+    by_name
+}
+
+fn is_defined(type_: &Types) -> bool {
+    match type_ {
+        Types::Struct(s) => s.is_definition,
+        Types::Union(u) => u.is_definition,
+        Types::Enum(e) => e.is_definition,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, names, struct_, types};
+    use serde_json::json;
+
+    /// A struct with a field of each of `field_types`.
+    fn struct_of(name: &str, size: Option<usize>, field_types: &[&str]) -> serde_json::Value {
+        let fields = field_types
+            .iter()
+            .map(|type_| field(Some("f"), type_, None))
+            .collect();
+        struct_(name, size, fields)
+    }
+
+    #[test]
+    fn references_leave_out_keywords() {
+        let types = types(json!([struct_of(
+            "foo",
+            Some(8),
+            &[
+                "const struct bar *[4]",
+                "unsigned long",
+                "foo::kind",
+                "::global"
+            ]
+        )]));
+        let references: Vec<String> = references(&types[0]).into_iter().collect();
+        assert_eq!(references, ["::global", "bar", "foo::kind"]);
+    }
+
+    #[test]
+    fn references_of_functions() {
+        let types = types(json!([{"Function": {
+            "name": "f", "location": null, "return_type": "ret_t", "return_info": {},
+            "parameters": [{"name": null, "type_": "const arg_t *"}], "is_variadic": false,
+        }}]));
+        let references: Vec<String> = references(&types[0]).into_iter().collect();
+        assert_eq!(references, ["arg_t", "ret_t"]);
+    }
+
+    #[test]
+    fn prune_keeps_what_roots_refer_to() {
+        let types = types(json!([
+            struct_of("unrelated", Some(4), &["int"]),
+            struct_of("leaf", Some(4), &["int"]),
+            struct_of("root", Some(8), &["struct middle *"]),
+            struct_of("middle", Some(16), &["struct leaf", "struct root *"]),
+        ]));
+        let pruned = prune(types, &["root"]).unwrap();
+        assert_eq!(names(&pruned), ["leaf", "root", "middle"]);
+    }
+
+    #[test]
+    fn prune_fails_on_unknown_roots() {
+        let types = types(json!([struct_of("root", Some(4), &["int"])]));
+        let e = prune(types, &["missing"]).unwrap_err();
+        assert_eq!(e, UnknownRoot("missing".to_string()));
+    }
+
+    #[test]
+    fn unresolved_types() {
+        let types = types(json!([
+            struct_of(
+                "root",
+                Some(64),
+                &[
+                    "struct opaque *",
+                    "FILE *",
+                    "struct defined",
+                    "__uint128_t",
+                    "const wchar_t *",
+                    "_Complex double",
+                ]
+            ),
+            struct_of("opaque", None, &[]),
+            struct_of("defined", None, &[]),
+            struct_of("defined", Some(0), &[]),
+        ]));
+        let unresolved: Vec<String> = unresolved(&types).into_iter().collect();
+        assert_eq!(unresolved, ["FILE", "opaque"]);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{StructType, Types};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The memory layout of a struct, with the padding between and after its
+/// fields made explicit.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub name: String,
+    pub size: usize,
+    pub alignment: Option<usize>,
+    /// The fields and padding of the struct in order of their offset, which
+    /// tile `size` exactly.
+    pub entries: Vec<LayoutEntry>,
+}
+
+/// A field of a struct, consecutive bit-fields sharing storage or padding
+/// inserted by the compiler.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct LayoutEntry {
+    /// The name of the field, or `None` for padding, bit-fields and anonymous
+    /// members.
+    pub name: Option<String>,
+    /// The type of the field, or of the first of the bit-fields, or `None`
+    /// for padding.
+    pub type_: Option<String>,
+    pub offset: usize,
+    pub size: usize,
+    /// The bit-fields stored in the bytes of this entry, if it is made up of
+    /// bit-fields.
+    #[serde(default)]
+    pub bit_fields: Vec<LayoutBitField>,
+}
+
+/// A bit-field stored in a `LayoutEntry`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct LayoutBitField {
+    pub name: Option<String>,
+    pub type_: String,
+    /// Offset of the bit-field from the start of the entry in bits.
+    pub bit_offset: usize,
+    pub width: usize,
+}
+
+impl LayoutEntry {
+    pub fn is_padding(&self) -> bool {
+        self.type_.is_none()
+    }
+
+    fn padding(offset: usize, size: usize) -> Self {
+        LayoutEntry {
+            name: None,
+            type_: None,
+            offset,
+            size,
+            bit_fields: Vec::new(),
+        }
+    }
+}
+
+/// Returns the layout of every struct in `types` whose layout is known.
+pub fn layouts(types: &[Types]) -> Vec<Layout> {
+    types
+        .iter()
+        .filter_map(|type_| match type_ {
+            Types::Struct(s) => layout(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the layout of `struct_`, or `None` if it is incomplete or the
+/// offset of any of its fields is unknown.
+pub fn layout(struct_: &StructType) -> Option<Layout> {
+    let size = struct_.size?;
+
+    let mut entries: Vec<LayoutEntry> = Vec::new();
+    let mut end = 0;
+    for field in &struct_.fields {
+        if let (Some(width), Some(bit_offset)) = (field.bit_width, field.bit_offset) {
+            // Unnamed bit-fields of width 0 only affect the offset of the next
+            // bit-field.
+            if width == 0 {
+                continue;
+            }
+            let start = bit_offset / 8;
+            let field_end = (bit_offset + width).div_ceil(8);
+            match entries.last_mut() {
+                // Bit-fields are merged with the preceding ones as long as
+                // there is no padding between them.
+                Some(last) if !last.bit_fields.is_empty() && start <= end => {
+                    last.size = field_end.max(end) - last.offset;
+                }
+                _ => {
+                    if start > end {
+                        entries.push(LayoutEntry::padding(end, start - end));
+                    }
+                    entries.push(LayoutEntry {
+                        name: None,
+                        type_: Some(field.type_.clone()),
+                        offset: start,
+                        size: field_end - start,
+                        bit_fields: Vec::new(),
+                    });
+                }
+            }
+            let last = entries.last_mut().unwrap();
+            last.bit_fields.push(LayoutBitField {
+                name: field.name.clone(),
+                type_: field.type_.clone(),
+                bit_offset: bit_offset - last.offset * 8,
+                width,
+            });
+            end = end.max(field_end);
+            continue;
+        }
+
+        let offset = field.offset?;
+        if offset > end {
+            entries.push(LayoutEntry::padding(end, offset - end));
+        }
+        // A flexible array member has no size of its own.
+        let field_size = field.info.size.unwrap_or(0);
+        entries.push(LayoutEntry {
+            name: field.name.clone(),
+            type_: Some(field.type_.clone()),
+            offset,
+            size: field_size,
+            bit_fields: Vec::new(),
+        });
+        end = end.max(offset + field_size);
+    }
+    if size > end {
+        entries.push(LayoutEntry::padding(end, size - end));
+    }
+
+    Some(Layout {
+        name: struct_.name.clone(),
+        size,
+        alignment: struct_.alignment,
+        entries,
+    })
+}
+
+/// Returns the ABI hash of every struct in `types` whose layout is known,
+/// keyed by its name.
+pub fn abi_hashes(types: &[Types]) -> BTreeMap<String, String> {
+    layouts(types)
+        .into_iter()
+        .map(|layout| {
+            let hash = abi_hash(&layout);
+            (layout.name, hash)
+        })
+        .collect()
+}
+
+/// Returns a hash of `layout` that only changes when its memory layout does,
+/// i.e. its size, alignment or the offset and size of any field, but not when
+/// a field or type is renamed. It is the same across runs and versions of Rust,
+/// so it can be compared with the hash of a previous build.
+pub fn abi_hash(layout: &Layout) -> String {
+    let mut words = vec![layout.size, layout.alignment.unwrap_or(0)];
+    for entry in &layout.entries {
+        words.extend(&[entry.offset, entry.size, entry.is_padding() as usize]);
+        for bit_field in &entry.bit_fields {
+            words.extend(&[bit_field.bit_offset, bit_field.width]);
+        }
+    }
+    format!("{:016x}", fnv1a(&words))
+}
+
+/// The 64-bit FNV-1a hash of `words`, which unlike `DefaultHasher` is
+/// guaranteed to be stable.
+fn fnv1a(words: &[usize]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for word in words {
+        for byte in &(*word as u64).to_le_bytes() {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, struct_, types};
+    use serde_json::json;
+
+    /// The name, type, offset, size and, for bit-fields, bit offset and width
+    /// of a field.
+    type Field<'a> = (&'a str, &'a str, usize, usize, Option<(usize, usize)>);
+
+    fn record(size: usize, fields: &[Field]) -> StructType {
+        let fields = fields
+            .iter()
+            .map(|&(name, type_, offset, size, bits)| {
+                let mut field = field(Some(name), type_, Some(offset));
+                field["size"] = json!(size);
+                field["bit_offset"] = json!(bits.map(|(offset, _)| offset));
+                field["bit_width"] = json!(bits.map(|(_, width)| width));
+                field
+            })
+            .collect();
+        let mut struct_ = struct_("s", Some(size), fields);
+        struct_["Struct"]["alignment"] = json!(4);
+        match types(json!([struct_])).remove(0) {
+            Types::Struct(struct_) => struct_,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the name, offset and size of every entry.
+    fn entries(layout: &Layout) -> Vec<(Option<&str>, usize, usize)> {
+        layout
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_deref(), entry.offset, entry.size))
+            .collect()
+    }
+
+    #[test]
+    fn padding_between_and_after_fields() {
+        let layout = layout(&record(
+            12,
+            &[
+                ("a", "char", 0, 1, None),
+                ("b", "int", 4, 4, None),
+                ("c", "char", 8, 1, None),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            entries(&layout),
+            [
+                (Some("a"), 0, 1),
+                (None, 1, 3),
+                (Some("b"), 4, 4),
+                (Some("c"), 8, 1),
+                (None, 9, 3),
+            ]
+        );
+        assert!(layout.entries[1].is_padding());
+        assert!(!layout.entries[2].is_padding());
+    }
+
+    #[test]
+    fn bit_fields_share_an_entry() {
+        // struct { unsigned a : 3; unsigned b : 7; unsigned : 0; char c : 2; int d; }
+        let layout = layout(&record(
+            12,
+            &[
+                ("a", "unsigned int", 0, 4, Some((0, 3))),
+                ("b", "unsigned int", 0, 4, Some((3, 7))),
+                ("", "unsigned int", 0, 4, Some((32, 0))),
+                ("c", "char", 4, 1, Some((32, 2))),
+                ("d", "int", 8, 4, None),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            entries(&layout),
+            [
+                (None, 0, 2),
+                (None, 2, 2),
+                (None, 4, 1),
+                (None, 5, 3),
+                (Some("d"), 8, 4)
+            ]
+        );
+
+        let bit_fields: Vec<_> = layout.entries[0]
+            .bit_fields
+            .iter()
+            .map(|b| (b.name.as_deref(), b.bit_offset, b.width))
+            .collect();
+        assert_eq!(bit_fields, [(Some("a"), 0, 3), (Some("b"), 3, 7)]);
+        assert_eq!(layout.entries[2].bit_fields[0].bit_offset, 0);
+        assert_eq!(layout.entries[0].type_.as_deref(), Some("unsigned int"));
+    }
+
+    #[test]
+    fn entries_tile_the_struct() {
+        let layout = layout(&record(
+            8,
+            &[
+                ("a", "char", 0, 1, Some((0, 1))),
+                ("b", "short", 2, 2, None),
+                ("c", "int", 4, 4, Some((36, 20))),
+            ],
+        ))
+        .unwrap();
+        let mut end = 0;
+        for entry in &layout.entries {
+            assert_eq!(entry.offset, end);
+            end += entry.size;
+        }
+        assert_eq!(end, layout.size);
+    }
+
+    #[test]
+    fn incomplete_structs_have_no_layout() {
+        let mut struct_ = record(4, &[("a", "int", 0, 4, None)]);
+        struct_.size = None;
+        assert_eq!(layout(&struct_), None);
+    }
+
+    #[test]
+    fn abi_hash_ignores_names() {
+        let a = layout(&record(
+            8,
+            &[("a", "int", 0, 4, None), ("b", "int", 4, 4, None)],
+        ))
+        .unwrap();
+        let renamed = layout(&record(
+            8,
+            &[("x", "int", 0, 4, None), ("y", "float", 4, 4, None)],
+        ))
+        .unwrap();
+        let moved = layout(&record(
+            8,
+            &[("a", "int", 0, 4, None), ("b", "short", 4, 2, None)],
+        ))
+        .unwrap();
+        assert_eq!(abi_hash(&a), abi_hash(&renamed));
+        assert_ne!(abi_hash(&a), abi_hash(&moved));
+    }
+
+    #[test]
+    fn abi_hash_is_stable() {
+        let layout = layout(&record(4, &[("a", "int", 0, 4, None)])).unwrap();
+        // Changing this breaks comparisons with hashes of previous versions.
+        assert_eq!(abi_hash(&layout), "9fd0ef5d653092c1");
+        // The FNV-1a hash of the empty input is its offset basis.
+        assert_eq!(fnv1a(&[]), 0xcbf2_9ce4_8422_2325);
+    }
+}
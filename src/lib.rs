@@ -0,0 +1,201 @@
+use flate2::read::GzDecoder;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "clang")]
+pub use clang;
+
+pub mod delta;
+pub mod emit;
+pub mod graph;
+pub mod layout;
+pub mod merge;
+pub mod model;
+#[cfg(feature = "clang")]
+pub mod naming;
+#[cfg(feature = "clang")]
+pub mod parser;
+#[cfg(test)]
+mod testing;
+
+pub use model::*;
+#[cfg(feature = "clang")]
+pub use parser::*;
+
+/// The ways in which parsing a header or loading previous output can fail.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The header or JSON file does not exist.
+    FileNotFound(PathBuf),
+    /// libclang could not be loaded. Builds without the default `runtime`
+    /// feature link to libclang instead, and fail to start if it is missing.
+    ClangInit(String),
+    /// libclang is already in use by another parse, e.g. on another thread,
+    /// which it does not allow.
+    ClangInUse,
+    /// Clang failed to produce a translation unit for the header, e.g. due to
+    /// invalid arguments. Errors in the header itself do not cause this, see
+    /// `ParseOptions::check`.
+    TranslationUnit { path: PathBuf, message: String },
+    /// Unnamed types were skipped while `OnSkip::Fail` was in effect. Holds
+    /// the location of every skipped type.
+    UnnamedEntity(Vec<String>),
+    /// Reading a file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// A file is not JSON emitted by a compatible version of ctypeparser.
+    InvalidJson {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::FileNotFound(path) => write!(f, "{} does not exist", path.display()),
+            ParseError::ClangInit(message) => write!(
+                f,
+                "failed to initialize libclang: {}\n\
+                 libclang must be installed, e.g. from the libclang-dev or clang package, \
+                 and is looked for in the usual library paths. Set LIBCLANG_PATH to the \
+                 directory containing libclang.so, libclang.dylib or libclang.dll if it \
+                 is installed elsewhere.",
+                message
+            ),
+            ParseError::ClangInUse => {
+                f.write_str("failed to initialize libclang: an instance is already in use")
+            }
+            ParseError::TranslationUnit { path, message } => {
+                write!(f, "failed to parse {}: {}", path.display(), message)
+            }
+            ParseError::UnnamedEntity(locations) => {
+                write!(f, "{} unnamed type(s) skipped", locations.len())
+            }
+            ParseError::Io { path, .. } => write!(f, "failed to read {}", path.display()),
+            ParseError::InvalidJson { path, .. } => write!(
+                f,
+                "{} is not JSON emitted by a compatible version of ctypeparser",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io { source, .. } => Some(source),
+            ParseError::InvalidJson { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// A value of an option given as a string, e.g. an output format, that is not
+/// one of the values the option accepts.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownValue {
+    /// What the value was given for, e.g. `format`.
+    pub option: &'static str,
+    pub value: String,
+    /// The values that are accepted, e.g. `error or rename`.
+    pub expected: String,
+}
+
+impl fmt::Display for UnknownValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown {} `{}`, expected {}",
+            self.option, self.value, self.expected
+        )
+    }
+}
+
+impl Error for UnknownValue {}
+
+/// Loads types previously emitted as JSON, e.g. by `ctypeparser --emit json`.
+/// Files ending in `.gz` are decompressed first.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Types>, ParseError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|source| match source.kind() {
+        io::ErrorKind::NotFound => ParseError::FileNotFound(path.to_path_buf()),
+        _ => ParseError::Io {
+            path: path.to_path_buf(),
+            source,
+        },
+    })?;
+    let reader: Box<dyn Read> = if path.extension() == Some("gz".as_ref()) {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    serde_json::from_reader(BufReader::new(reader)).map_err(|source| {
+        // Decompression errors surface as I/O errors of the JSON reader.
+        if source.is_io() {
+            ParseError::Io {
+                path: path.to_path_buf(),
+                source: source.into(),
+            }
+        } else {
+            ParseError::InvalidJson {
+                path: path.to_path_buf(),
+                source,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const JSON: &[u8] = br#"[{"Variable": {"name": "v", "location": null, "type_": "int"}}]"#;
+
+    /// Writes `contents` to a file named `name` in a temporary directory.
+    fn write(name: &str, contents: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ctypeparser-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Loads `contents` as if from a file named `name`.
+    fn load_from(name: &str, contents: &[u8]) -> Result<Vec<Types>, ParseError> {
+        let path = write(name, contents);
+        let result = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn load_json() {
+        let types = load_from("load.json", JSON).unwrap();
+        assert!(matches!(&types[..], [Types::Variable(v)] if v.name == "v"));
+    }
+
+    #[test]
+    fn load_errors() {
+        let path = std::env::temp_dir().join("ctypeparser-missing.json");
+        assert!(matches!(load(&path), Err(ParseError::FileNotFound(p)) if p == path));
+        let result = load_from("invalid.json", b"[{");
+        assert!(matches!(result, Err(ParseError::InvalidJson { .. })));
+    }
+
+    #[test]
+    fn load_gzipped_json() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(JSON).unwrap();
+        let gzipped = load_from("load.json.gz", &encoder.finish().unwrap()).unwrap();
+        assert_eq!(gzipped, load_from("load.json", JSON).unwrap());
+
+        let result = load_from("invalid.json.gz", b"not gzip");
+        assert!(matches!(result, Err(ParseError::Io { .. })));
+    }
+}
@@ -1,164 +1,483 @@
-use anyhow::Result;
-use clang::{Clang, Entity, EntityKind, EntityVisitResult, Index};
-use serde::{Deserialize, Serialize};
-
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-enum Types {
-    TypeDef(TypeDefType),
-    Struct(StructType),
-    Enum(EnumType),
-    Union(UnionType),
-}
+use anyhow::{bail, Context, Result};
+use ctypeparser::delta;
+use ctypeparser::emit::{self, Format};
+use ctypeparser::graph;
+use ctypeparser::merge::{self, OnConflict};
+use ctypeparser::{OnSkip, Order, ParseOptions, Types};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct TypeDefType {
-    name: String,
-    underlying: String,
-}
+const USAGE: &str = "usage: ctypeparser [options] <header>... [-- <clang args>...]
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct StructType {
-    name: String,
-    fields: Vec<StructField>,
-}
+options:
+    --check                      only report clang diagnostics, failing on errors
+    --watch                      re-run whenever the headers change
+    --names                      only print the name of every type, one per line
+    --with-kind                  prefix names printed by --names with their kind
+    --unresolved                 only print the names of types that are referred
+                                 to but not defined, one per line
+    --target <triple>            parse for the given target
+    --resource-dir <dir>         directory of clang's builtin headers, e.g.
+                                 stddef.h
+    --define-all-from <file>     define every NAME or NAME=VALUE in <file>, one
+                                 per line, as with -D
+    --include-system             include types declared outside of the headers
+    --no-follow-definitions      don't replace declarations with their definition
+    --emit <format>              output format, one of json, rust, table, layout,
+                                 indexed, canonical or abi-hash, may be given
+                                 more than once
+    --layout                     emit struct layouts including padding, same as
+                                 --emit layout
+    --indexed                    emit a JSON object keyed by type name, same as
+                                 --emit indexed
+    --canonical                  emit deterministic JSON for hashing, same as
+                                 --emit canonical
+    --abi-hash                   emit a hash of every struct's layout that only
+                                 changes with its ABI, same as --emit abi-hash
+    --root <name>                only keep <name> and the types it depends on,
+                                 may be given more than once
+    --since <json>               only emit types added or changed since the
+                                 output in <json>, listing removed types on
+                                 stderr
+    --self-check                 check that the JSON output loads back unchanged,
+                                 failing loudly if it does not
+    --out-dir <dir>              write each format to a file in <dir>
+    --gzip                       compress the output, adding .gz to file names
+    --on-conflict <error|rename> handling of types defined differently in
+                                 several headers
+    --anon-naming <naming>       naming of anonymous types, one of qualified,
+                                 index, counter, camel or a template
+    --hex-values                 also give enum values in hexadecimal
+    --array-size-spellings       also give array lengths as spelled in the headers
+    --breadth-first              output nested types after all top-level types
+    --depth-first                output nested types before the record they are
+                                 nested in (the default)
+    --with-locations             record where every type is declared
+    --relative-to <base>         make recorded paths relative to <base>
+    --warn-on-skip               warn about unnamed types that are left out
+    --fail-on-unnamed            fail if any unnamed types are left out
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct StructField {
-    name: String,
-    type_: String,
-}
+Objective-C headers are parsed with `-- -x objective-c`, emitting the instance
+variables of every @interface as a struct.
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct EnumType {
-    name: String,
-    fields: Vec<EnumField>,
-}
+Types declared in any of the headers are kept, even in a header that is included
+by another, while those of other included headers are left out.
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct EnumField {
-    name: String,
-    value: i64,
-}
+Whitespace-separated clang arguments in the CTYPEPARSER_CLANG_ARGS environment
+variable are passed to clang before those given after `--`, so the latter take
+precedence. Both take precedence over --target and --resource-dir, while
+--define-all-from is applied in between.";
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct UnionType {
-    name: String,
-    fields: Vec<UnionField>,
-}
+/// Environment variable holding default clang arguments, e.g. include paths
+/// that are needed for every invocation.
+const CLANG_ARGS_VAR: &str = "CTYPEPARSER_CLANG_ARGS";
+
+/// How long to wait for further changes before re-parsing, so that an editor
+/// saving in several steps only triggers a single run.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
 
-#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
-struct UnionField {
-    name: String,
-    type_: String,
+struct Args {
+    options: ParseOptions,
+    fnames: Vec<String>,
+    check: bool,
+    watch: bool,
+    names: bool,
+    with_kind: bool,
+    unresolved: bool,
+    formats: Vec<Format>,
+    out_dir: Option<PathBuf>,
+    gzip: bool,
+    on_conflict: OnConflict,
+    roots: Vec<String>,
+    since: Option<PathBuf>,
+    self_check: bool,
 }
 
 fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    let fname = args.get(1).unwrap();
+    let args = parse_args()?;
+    if args.watch {
+        watch(&args)
+    } else {
+        run(&args)
+    }
+}
+
+fn parse_args() -> Result<Args> {
+    let mut options = ParseOptions::new();
+    let mut check = false;
+    let mut watch = false;
+    let mut names = false;
+    let mut with_kind = false;
+    let mut unresolved = false;
+    let mut formats = Vec::new();
+    let mut out_dir = None;
+    let mut gzip = false;
+    let mut on_conflict = OnConflict::Error;
+    let mut roots = Vec::new();
+    let mut since = None;
+    let mut self_check = false;
+    let mut fnames = Vec::new();
+
+    // Clang lets later arguments override earlier ones, so the environment
+    // must come first for the command line to take precedence.
+    if let Ok(env_args) = std::env::var(CLANG_ARGS_VAR) {
+        let env_args: Vec<&str> = env_args.split_whitespace().collect();
+        options = options.clang_args(&env_args);
+    }
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--watch" => watch = true,
+            "--names" => names = true,
+            "--with-kind" => with_kind = true,
+            "--unresolved" => unresolved = true,
+            "--target" => {
+                let target = args.next().context("--target requires a value")?;
+                options = options.target(target);
+            }
+            "--resource-dir" => {
+                let dir = args.next().context("--resource-dir requires a value")?;
+                options = options.resource_dir(dir);
+            }
+            "--emit" | "--format" => {
+                let format = args
+                    .next()
+                    .with_context(|| format!("{} requires a value", arg))?;
+                formats.push(format.parse::<Format>()?);
+            }
+            "--layout" => formats.push(Format::Layout),
+            "--indexed" => formats.push(Format::Indexed),
+            "--canonical" => formats.push(Format::Canonical),
+            "--abi-hash" => formats.push(Format::AbiHash),
+            "--root" => roots.push(args.next().context("--root requires a value")?),
+            "--since" => {
+                let previous = args.next().context("--since requires a value")?;
+                since = Some(PathBuf::from(previous));
+            }
+            "--self-check" => self_check = true,
+            "--out-dir" => {
+                let dir = args.next().context("--out-dir requires a value")?;
+                out_dir = Some(PathBuf::from(dir));
+            }
+            "--gzip" => gzip = true,
+            "--on-conflict" => {
+                let value = args.next().context("--on-conflict requires a value")?;
+                on_conflict = value.parse()?;
+            }
+            "--anon-naming" => {
+                let value = args.next().context("--anon-naming requires a value")?;
+                options = options.anon_naming(value.parse()?);
+            }
+            "--hex-values" => options = options.hex_values(true),
+            "--array-size-spellings" => options = options.array_size_spellings(true),
+            "--breadth-first" => options = options.order(Order::BreadthFirst),
+            "--depth-first" => options = options.order(Order::DepthFirst),
+            "--with-locations" => options = options.with_locations(true),
+            "--relative-to" => {
+                let base = args.next().context("--relative-to requires a value")?;
+                options = options.relative_to(base);
+            }
+            "--warn-on-skip" => options = options.on_skip(OnSkip::Warn),
+            "--fail-on-unnamed" => options = options.on_skip(OnSkip::Fail),
+            "--define-all-from" => {
+                let fname = args.next().context("--define-all-from requires a value")?;
+                options = options.clang_args(&read_defines(&fname)?);
+            }
+            "--include-system" => options = options.include_system(true),
+            "--no-follow-definitions" => options = options.follow_definitions(false),
+            "--" => {
+                let clang_args: Vec<String> = args.by_ref().collect();
+                options = options.clang_args(&clang_args);
+            }
+            _ if arg.starts_with('-') => bail!("unknown option `{}`\n{}", arg, USAGE),
+            _ => fnames.push(arg),
+        }
+    }
+    if fnames.is_empty() {
+        bail!(USAGE);
+    }
+    // Every header given is of interest, even when it is included by another.
+    if fnames.len() > 1 {
+        options = options.main_files(&fnames);
+    }
 
-    let clang = Clang::new().unwrap();
-    let index = Index::new(&clang, true, true);
-    let parser = index.parser(fname);
-    let translation_unit = parser.parse()?;
+    if formats.is_empty() {
+        formats.push(Format::Json);
+    }
+    if formats.len() > 1 && out_dir.is_none() {
+        bail!("emitting more than one format requires --out-dir");
+    }
 
-    let mut types = Vec::new();
-    let entity = translation_unit.get_entity();
+    Ok(Args {
+        options,
+        fnames,
+        check,
+        watch,
+        names,
+        with_kind,
+        unresolved,
+        formats,
+        out_dir,
+        gzip,
+        on_conflict,
+        roots,
+        since,
+        self_check,
+    })
+}
 
-    let _ = entity.visit_children(|entity, parent| -> EntityVisitResult {
-        // Use the "definition" of the entity if it exists. This handles the
-        // case of forward declarations.
-        let e = entity.get_definition().unwrap_or(entity);
+fn run(args: &Args) -> Result<()> {
+    if args.check {
+        return check_diagnostics(&args.options, &args.fnames);
+    }
 
-        if !e.is_in_main_file() {
-            return EntityVisitResult::Continue;
+    let types = if args.fnames.len() == 1 {
+        parse(&args.options, &args.fnames[0])?
+    } else {
+        let parsed = args
+            .fnames
+            .iter()
+            .map(|fname| Ok((fname, parse(&args.options, fname)?)))
+            .collect::<Result<Vec<_>>>()?;
+        merge::merge(parsed, args.on_conflict)?
+    };
+    let types = if args.roots.is_empty() {
+        types
+    } else {
+        let types = graph::prune(types, &args.roots)?;
+        // The output is not self-contained without these.
+        if !args.unresolved {
+            for name in graph::unresolved(&types) {
+                eprintln!("warning: `{}` is referenced but not defined", name);
+            }
+        }
+        types
+    };
+    if args.unresolved {
+        let mut stdout = io::stdout().lock();
+        for name in graph::unresolved(&types) {
+            writeln!(stdout, "{}", name)?;
         }
+        return Ok(());
+    }
+    let types = match &args.since {
+        Some(previous) => {
+            let delta = delta::delta(&ctypeparser::load(previous)?, &types);
+            for (kind, name) in &delta.removed {
+                eprintln!("removed: {} {}", kind, name);
+            }
+            delta.changed
+        }
+        None => types,
+    };
+    if args.self_check {
+        emit::self_check(&types)?;
+    }
+    if args.names {
+        return print_names(&types, args.with_kind);
+    }
+    for &format in &args.formats {
+        let output = emit::emit(format, &types)?;
+        match &args.out_dir {
+            Some(dir) => {
+                let path = output_path(dir, &args.fnames[0], format, args.gzip);
+                let file = File::create(&path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+                write_output(file, &output, args.gzip)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+            }
+            None => write_output(io::stdout().lock(), &output, args.gzip)?,
+        }
+    }
+    Ok(())
+}
 
-        match e.get_kind() {
-            EntityKind::TypedefDecl => parse_typedef(&e, &mut types),
-            EntityKind::StructDecl => parse_struct(&e, &parent, &mut types),
-            EntityKind::EnumDecl => parse_enum(&e, &parent, &mut types),
-            EntityKind::UnionDecl => parse_union(&e, &parent, &mut types),
-            _ => {}
-        };
-        EntityVisitResult::Continue
-    });
-
-    let json = serde_json::to_string(&types)?;
-    println!("{}", json);
+/// Parses the header `fname`, printing any warnings.
+fn parse(options: &ParseOptions, fname: &str) -> Result<Vec<Types>> {
+    let (types, warnings) = options.parse_with_warnings(fname)?;
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    Ok(types)
+}
+
+fn print_names(types: &[Types], with_kind: bool) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    for type_ in types {
+        if with_kind {
+            writeln!(stdout, "{} {}", type_.kind(), type_.name())?;
+        } else {
+            writeln!(stdout, "{}", type_.name())?;
+        }
+    }
     Ok(())
 }
 
-fn parse_typedef(entity: &Entity, types: &mut Vec<Types>) {
-    let name = entity.get_name().unwrap();
-    let underlying = entity
-        .get_typedef_underlying_type()
-        .unwrap()
-        .get_display_name();
-    types.push(Types::TypeDef(TypeDefType { name, underlying }))
+fn write_output<W: Write>(mut writer: W, output: &str, gzip: bool) -> io::Result<()> {
+    if gzip {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        encoder.write_all(output.as_bytes())?;
+        encoder.finish()?.flush()
+    } else {
+        writer.write_all(output.as_bytes())?;
+        writer.flush()
+    }
 }
 
-fn get_name(entity: &Entity, parent: &Entity) -> Option<String> {
-    match entity.get_name() {
-        Some(n) => Some(n),
-        None => {
-            if let EntityKind::TypedefDecl = parent.get_kind() {
-                parent.get_name()
-            } else {
-                None
+/// Runs once, then again every time one of the headers or a header they
+/// include changes on disk.
+fn watch(args: &Args) -> Result<()> {
+    loop {
+        if let Err(e) = run(args) {
+            eprintln!("Error: {:?}", e);
+        }
+
+        // The set of includes may have changed since the last run.
+        let mut files = BTreeSet::new();
+        for fname in &args.fnames {
+            match args.options.dependencies(fname) {
+                Ok(dependencies) => files.extend(dependencies),
+                // The header may be briefly missing while an editor saves it,
+                // so it can't be canonicalized, yet events refer to it by its
+                // absolute path.
+                Err(_) => {
+                    let fname = std::env::current_dir()?.join(fname);
+                    files.insert(fname.canonicalize().unwrap_or(fname));
+                }
+            }
+        }
+
+        // Editors commonly save by replacing the file, which would remove a
+        // watch on the file itself, so the directories containing the files
+        // are watched instead.
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::watcher(tx, WATCH_DEBOUNCE)?;
+        let dirs: BTreeSet<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+        for dir in dirs {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("Error: failed to watch {}: {:?}", dir.display(), e);
             }
         }
+
+        loop {
+            let changed = match rx.recv()? {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Remove(path)
+                | DebouncedEvent::Rename(_, path) => files.contains(&path),
+                DebouncedEvent::Error(e, _) => {
+                    eprintln!("Error: {:?}", e);
+                    false
+                }
+                _ => false,
+            };
+            if changed {
+                break;
+            }
+        }
+        println!("{}", "-".repeat(80));
     }
 }
 
-fn parse_struct(entity: &Entity, parent: &Entity, types: &mut Vec<Types>) {
-    let name = get_name(&entity, &parent);
-    if let Some(name) = name {
-        let fields: Vec<StructField> = entity
-            .get_children()
-            .iter()
-            .map(|field| StructField {
-                name: field.get_name().unwrap(),
-                type_: field.get_type().unwrap().get_display_name(),
-            })
-            .collect();
+/// Reads the defines in `fname` as `-D` clang arguments. Every line defines a
+/// macro like `NAME=VALUE` or just `NAME`, while empty lines and lines starting
+/// with `#` are ignored.
+fn read_defines(fname: &str) -> Result<Vec<String>> {
+    let contents =
+        std::fs::read_to_string(fname).with_context(|| format!("failed to read {}", fname))?;
+    parse_defines(fname, &contents)
+}
 
-        types.push(Types::Struct(StructType { name, fields }));
+/// Parses the defines in `contents`, which were read from `fname`, see
+/// `read_defines`.
+fn parse_defines(fname: &str, contents: &str) -> Result<Vec<String>> {
+    let mut defines = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let name = line.split('=').next().unwrap_or_default().trim_end();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!(
+                "{}:{}: expected NAME or NAME=VALUE, got `{}`",
+                fname,
+                i + 1,
+                line
+            );
+        }
+        defines.push(format!("-D{}", line));
     }
+    Ok(defines)
 }
 
-fn parse_enum(entity: &Entity, parent: &Entity, types: &mut Vec<Types>) {
-    let name = get_name(&entity, &parent);
-    if let Some(name) = name {
-        let fields: Vec<EnumField> = entity
-            .get_children()
-            .iter()
-            .map(|field| {
-                // We make an assumption here that an enum is always a
-                // signed value.
-                let (value, _) = field.get_enum_constant_value().unwrap();
-                EnumField {
-                    name: field.get_name().unwrap(),
-                    value,
-                }
-            })
-            .collect();
+/// Returns the path `format` is written to in `dir`, named after the (first)
+/// header, e.g. `<dir>/foo.rs` for `foo.h`.
+fn output_path(dir: &Path, fname: &str, format: Format, gzip: bool) -> PathBuf {
+    let stem = Path::new(fname).file_stem().unwrap_or_default();
+    let path = dir.join(stem).with_extension(format.extension());
+    if gzip {
+        let mut path = path.into_os_string();
+        path.push(".gz");
+        path.into()
+    } else {
+        path
+    }
+}
 
-        types.push(Types::Enum(EnumType { name, fields }));
+/// Reports every diagnostic clang produced for the headers and fails if any
+/// of them are errors.
+fn check_diagnostics(options: &ParseOptions, fnames: &[String]) -> Result<()> {
+    let mut errors = 0;
+    for fname in fnames {
+        for diagnostic in options.check(fname)? {
+            eprintln!("{}", diagnostic.message);
+            if diagnostic.is_error {
+                errors += 1;
+            }
+        }
+    }
+
+    if errors > 0 {
+        bail!("{} error(s) generated", errors);
     }
+    Ok(())
 }
 
-fn parse_union(entity: &Entity, parent: &Entity, types: &mut Vec<Types>) {
-    let name = get_name(&entity, &parent);
-    if let Some(name) = name {
-        let fields: Vec<UnionField> = entity
-            .get_children()
-            .iter()
-            .map(|field| UnionField {
-                name: field.get_name().unwrap(),
-                type_: field.get_type().unwrap().get_display_name(),
-            })
-            .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_paths() {
+        let path = output_path(Path::new("out"), "inc/foo.h", Format::Rust, false);
+        assert_eq!(path, Path::new("out/foo.rs"));
+        let path = output_path(Path::new("out"), "foo.h", Format::Json, true);
+        assert_eq!(path, Path::new("out/foo.json.gz"));
+    }
+
+    #[test]
+    fn defines() {
+        let defines = parse_defines("defines", "# comment\nFOO\n\n  BAR=1 \nBAZ = x y\n").unwrap();
+        assert_eq!(defines, ["-DFOO", "-DBAR=1", "-DBAZ = x y"]);
+    }
 
-        types.push(Types::Union(UnionType { name, fields }));
+    #[test]
+    fn invalid_defines() {
+        let e = parse_defines("defines", "FOO\nNOT A DEFINE\n").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "defines:2: expected NAME or NAME=VALUE, got `NOT A DEFINE`"
+        );
+        assert!(read_defines("/nonexistent/defines").is_err());
     }
 }
@@ -0,0 +1,244 @@
+use crate::{Types, UnknownValue};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::mem::{self, Discriminant};
+use std::str::FromStr;
+
+/// Identifies a type by its kind and name.
+type Key = (Discriminant<Types>, String);
+
+/// How to handle types parsed from different headers that share a name but
+/// are not defined identically.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnConflict {
+    /// Fail with an error naming both headers.
+    Error,
+    /// Keep every definition, suffixing the name of each with `@<header>`.
+    Rename,
+}
+
+impl FromStr for OnConflict {
+    type Err = UnknownValue;
+
+    fn from_str(s: &str) -> Result<Self, UnknownValue> {
+        match s {
+            "error" => Ok(OnConflict::Error),
+            "rename" => Ok(OnConflict::Rename),
+            _ => Err(UnknownValue {
+                option: "conflict handling",
+                value: s.to_string(),
+                expected: "error or rename".to_string(),
+            }),
+        }
+    }
+}
+
+/// A type that is defined differently in two headers while
+/// `OnConflict::Error` is in effect.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    pub name: String,
+    /// The header the type was first defined in.
+    pub first: String,
+    /// The header defining it differently.
+    pub second: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is defined differently in {} and {}",
+            self.name, self.first, self.second
+        )
+    }
+}
+
+impl Error for Conflict {}
+
+/// Merges the types parsed from several headers, given along with the path
+/// of the header they came from. Types defined identically in more than one
+/// header are only kept once, and forward declarations are dropped in
+/// favour of a definition from another header.
+///
+/// Structs and typedefs live in different namespaces in C, so
+/// `typedef struct foo foo;` is not a conflict.
+pub fn merge<S: AsRef<str>>(
+    parsed: Vec<(S, Vec<Types>)>,
+    on_conflict: OnConflict,
+) -> Result<Vec<Types>, Conflict> {
+    let mut merged: Vec<Types> = Vec::new();
+    // The position in `merged` and header of every definition seen so far.
+    let mut seen: HashMap<Key, Vec<(usize, &str)>> = HashMap::new();
+
+    for (header, types) in &parsed {
+        let header = header.as_ref();
+        for mut type_ in types.iter().cloned() {
+            let name = type_.name().to_string();
+            let definitions = seen
+                .entry((mem::discriminant(&type_), name.clone()))
+                .or_default();
+
+            if definitions
+                .iter()
+                .any(|&(i, _)| same_definition(&merged[i], &type_))
+            {
+                continue;
+            }
+
+            // A forward declaration is compatible with any definition of the
+            // type, which takes its place.
+            if is_declaration(&type_) && !definitions.is_empty() {
+                continue;
+            }
+            if let Some(&(i, _)) = definitions.first() {
+                if is_declaration(&merged[i]) {
+                    merged[i] = type_;
+                    definitions[0].1 = header;
+                    continue;
+                }
+            }
+
+            if let Some(&(i, first)) = definitions.first() {
+                match on_conflict {
+                    OnConflict::Error => {
+                        return Err(Conflict {
+                            name,
+                            first: first.to_string(),
+                            second: header.to_string(),
+                        })
+                    }
+                    OnConflict::Rename => {
+                        if definitions.len() == 1 {
+                            *merged[i].name_mut() = format!("{}@{}", name, first);
+                        }
+                        *type_.name_mut() = format!("{}@{}", name, header);
+                    }
+                }
+            }
+
+            definitions.push((merged.len(), header));
+            merged.push(type_);
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Returns whether `type_` is a record or enum that is declared but not
+/// defined, e.g. `struct foo;`.
+fn is_declaration(type_: &Types) -> bool {
+    match type_ {
+        Types::Struct(s) => !s.is_definition,
+        Types::Enum(e) => !e.is_definition,
+        Types::Union(u) => !u.is_definition,
+        _ => false,
+    }
+}
+
+/// Returns whether `a` and `b` are defined identically, ignoring any suffix
+/// added to their names by a previous conflict and where they are declared.
+fn same_definition(a: &Types, b: &Types) -> bool {
+    let mut a = a.clone();
+    *a.name_mut() = b.name().to_string();
+    *a.location_mut() = b.location().cloned();
+    a == *b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, names, struct_, types};
+    use serde_json::json;
+
+    fn enum_(name: &str, value: i64) -> Types {
+        types(json!([{"Enum": {
+            "name": name, "location": null, "is_definition": true, "underlying": "int",
+            "fields": [{"name": "A", "value": value, "is_out_of_range": false,
+                "value_hex": null}],
+        }}]))
+        .remove(0)
+    }
+
+    fn typedef(name: &str) -> Types {
+        types(json!([{"TypeDef": {
+            "name": name, "location": null, "underlying": format!("enum {}", name),
+        }}]))
+        .remove(0)
+    }
+
+    #[test]
+    fn identical_definitions_are_kept_once() {
+        let mut located = enum_("e", 0);
+        *located.location_mut() = Some(crate::Location {
+            file: "b.h".to_string(),
+            line: 1,
+            column: 1,
+        });
+        let parsed = vec![
+            ("a.h", vec![enum_("e", 0), typedef("e")]),
+            ("b.h", vec![located]),
+        ];
+        let merged = merge(parsed, OnConflict::Error).unwrap();
+        assert_eq!(names(&merged), ["e", "e"]);
+        assert_eq!(merged[1].kind(), "typedef");
+    }
+
+    #[test]
+    fn declarations_are_replaced_by_definitions() {
+        let declaration = || types(json!([struct_("foo", None, vec![])])).remove(0);
+        let definition = |type_| {
+            types(json!([struct_(
+                "foo",
+                Some(4),
+                vec![field(Some("x"), type_, Some(0))]
+            )]))
+            .remove(0)
+        };
+        let parsed = vec![
+            ("a.h", vec![declaration(), enum_("e", 0)]),
+            ("b.h", vec![definition("int"), declaration()]),
+            ("c.h", vec![definition("float")]),
+        ];
+        let merged = merge(parsed.clone(), OnConflict::Rename).unwrap();
+        assert_eq!(names(&merged), ["foo@b.h", "e", "foo@c.h"]);
+
+        let merged = merge(parsed[..2].to_vec(), OnConflict::Error).unwrap();
+        assert_eq!(merged, [definition("int"), enum_("e", 0)]);
+        let e = merge(parsed, OnConflict::Error).unwrap_err();
+        assert_eq!((e.first.as_str(), e.second.as_str()), ("b.h", "c.h"));
+    }
+
+    #[test]
+    fn conflicts_are_errors() {
+        let parsed = vec![("a.h", vec![enum_("e", 0)]), ("b.h", vec![enum_("e", 1)])];
+        let e = merge(parsed, OnConflict::Error).unwrap_err();
+        assert_eq!(
+            e,
+            Conflict {
+                name: "e".to_string(),
+                first: "a.h".to_string(),
+                second: "b.h".to_string(),
+            }
+        );
+        assert_eq!(e.to_string(), "`e` is defined differently in a.h and b.h");
+    }
+
+    #[test]
+    fn conflicts_are_renamed() {
+        let parsed = vec![
+            ("a.h", vec![enum_("e", 0)]),
+            ("b.h", vec![enum_("e", 1)]),
+            ("c.h", vec![enum_("e", 2), enum_("e", 0)]),
+        ];
+        let merged = merge(parsed, OnConflict::Rename).unwrap();
+        assert_eq!(names(&merged), ["e@a.h", "e@b.h", "e@c.h"]);
+    }
+
+    #[test]
+    fn parse_on_conflict() {
+        assert_eq!("rename".parse::<OnConflict>().unwrap(), OnConflict::Rename);
+        assert!("ignore".parse::<OnConflict>().is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! The types emitted for a header, which are serialized as ctypeparser's JSON
+//! output. They don't depend on clang, so they can be used to consume that
+//! output without it.
+
+use serde::{Deserialize, Serialize};
+
+// Fields added to the schema after its first version have defaults, so that
+// output of older versions can still be loaded.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Types {
+    TypeDef(TypeDefType),
+    Struct(StructType),
+    Enum(EnumType),
+    Union(UnionType),
+    Variable(VariableType),
+    Function(FunctionType),
+    Constant(ConstantType),
+}
+
+impl Types {
+    pub fn name(&self) -> &str {
+        match self {
+            Types::TypeDef(t) => &t.name,
+            Types::Struct(s) => &s.name,
+            Types::Enum(e) => &e.name,
+            Types::Union(u) => &u.name,
+            Types::Variable(v) => &v.name,
+            Types::Function(f) => &f.name,
+            Types::Constant(c) => &c.name,
+        }
+    }
+
+    /// Returns where the type is declared, if locations were recorded.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Types::TypeDef(t) => t.location.as_ref(),
+            Types::Struct(s) => s.location.as_ref(),
+            Types::Enum(e) => e.location.as_ref(),
+            Types::Union(u) => u.location.as_ref(),
+            Types::Variable(v) => v.location.as_ref(),
+            Types::Function(f) => f.location.as_ref(),
+            Types::Constant(c) => c.location.as_ref(),
+        }
+    }
+
+    /// Returns what kind of declaration this is, e.g. `struct`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Types::TypeDef(_) => "typedef",
+            Types::Struct(_) => "struct",
+            Types::Enum(_) => "enum",
+            Types::Union(_) => "union",
+            Types::Variable(_) => "variable",
+            Types::Function(_) => "function",
+            Types::Constant(_) => "constant",
+        }
+    }
+
+    pub(crate) fn location_mut(&mut self) -> &mut Option<Location> {
+        match self {
+            Types::TypeDef(t) => &mut t.location,
+            Types::Struct(s) => &mut s.location,
+            Types::Enum(e) => &mut e.location,
+            Types::Union(u) => &mut u.location,
+            Types::Variable(v) => &mut v.location,
+            Types::Function(f) => &mut f.location,
+            Types::Constant(c) => &mut c.location,
+        }
+    }
+
+    pub(crate) fn name_mut(&mut self) -> &mut String {
+        match self {
+            Types::TypeDef(t) => &mut t.name,
+            Types::Struct(s) => &mut s.name,
+            Types::Enum(e) => &mut e.name,
+            Types::Union(u) => &mut u.name,
+            Types::Variable(v) => &mut v.name,
+            Types::Function(f) => &mut f.name,
+            Types::Constant(c) => &mut c.name,
+        }
+    }
+}
+
+/// A position in a header.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Location {
+    /// The path of the header, relative to the base set with
+    /// `ParseOptions::relative_to` if it is inside of it.
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct TypeDefType {
+    pub name: String,
+    /// Where the type is declared, if locations were requested.
+    pub location: Option<Location>,
+    pub underlying: String,
+    /// Information about the underlying type, e.g. the dimensions of an
+    /// array for `typedef int vec3[3];`. Its `type_kind` is what the typedef
+    /// resolves to, e.g. `Record` for `typedef struct foo foo;` or `Pointer`
+    /// for `B` given `typedef char *A; typedef A B;`.
+    #[serde(flatten)]
+    pub info: TypeInfo,
+    /// The types that have to be looked through to get from this typedef to
+    /// a type that is not a typedef, starting with `underlying`, e.g.
+    /// `["B", "A", "int"]` for `C` given `typedef int A; typedef A B;
+    /// typedef B C;`.
+    #[serde(default)]
+    pub resolved_chain: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct StructType {
+    pub name: String,
+    /// Where the type is declared, if locations were requested.
+    pub location: Option<Location>,
+    /// Whether the type is defined, rather than only declared, in the parsed
+    /// headers.
+    #[serde(default)]
+    pub is_definition: bool,
+    pub size: Option<usize>,
+    pub alignment: Option<usize>,
+    /// The sum of the sizes of the fields, which is less than `size` by the
+    /// padding between and after them.
+    pub fields_size: Option<usize>,
+    /// The alignment explicitly requested with `alignas`, `_Alignas` or
+    /// `__attribute__((aligned))`, as opposed to the natural alignment of
+    /// the fields.
+    pub requested_alignment: Option<usize>,
+    /// Whether the members are packed more tightly than their alignment
+    /// requires, e.g. by `#pragma pack` or `__attribute__((packed))`.
+    #[serde(default)]
+    pub is_packed: bool,
+    pub fields: Vec<StructField>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct StructField {
+    /// The name of the member, or `None` for an anonymous struct or union
+    /// member whose fields are accessed as if they belonged to the parent.
+    pub name: Option<String>,
+    pub type_: String,
+    /// Offset of the field from the start of the struct in bytes.
+    pub offset: Option<usize>,
+    #[serde(flatten)]
+    pub info: TypeInfo,
+    /// The alignment explicitly requested with `alignas`, `_Alignas` or
+    /// `__attribute__((aligned))`, as opposed to the natural alignment of
+    /// the type.
+    pub requested_alignment: Option<usize>,
+    /// Whether the field is declared `_Atomic`.
+    #[serde(default)]
+    pub is_atomic: bool,
+    /// The width in bits of a bit-field.
+    pub bit_width: Option<usize>,
+    /// Offset of a bit-field from the start of the struct in bits, as
+    /// `offset` is only the byte its first bit is in.
+    pub bit_offset: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct EnumType {
+    pub name: String,
+    /// Where the type is declared, if locations were requested.
+    pub location: Option<Location>,
+    /// Whether the enum is defined, rather than only declared, in the parsed
+    /// headers. C++ allows declaring an enum with an underlying type before
+    /// defining its constants, e.g. `enum class Foo : int;`.
+    #[serde(default)]
+    pub is_definition: bool,
+    pub underlying: Option<String>,
+    pub fields: Vec<EnumField>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct EnumField {
+    pub name: String,
+    pub value: i64,
+    /// Whether the constant was initialized with a value that does not fit
+    /// the underlying type of the enum, making `value` suspect.
+    #[serde(default)]
+    pub is_out_of_range: bool,
+    /// `value` in hexadecimal, e.g. `0x80`, if requested with
+    /// `ParseOptions::hex_values`.
+    pub value_hex: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct UnionType {
+    pub name: String,
+    /// Where the type is declared, if locations were requested.
+    pub location: Option<Location>,
+    /// Whether the type is defined, rather than only declared, in the parsed
+    /// headers.
+    #[serde(default)]
+    pub is_definition: bool,
+    pub size: Option<usize>,
+    pub alignment: Option<usize>,
+    /// The alignment explicitly requested with `alignas`, `_Alignas` or
+    /// `__attribute__((aligned))`, as opposed to the natural alignment of
+    /// the members.
+    pub requested_alignment: Option<usize>,
+    /// Whether the members are packed more tightly than their alignment
+    /// requires, e.g. by `#pragma pack` or `__attribute__((packed))`.
+    #[serde(default)]
+    pub is_packed: bool,
+    pub fields: Vec<UnionField>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct UnionField {
+    /// The name of the member, or `None` for an anonymous struct or union
+    /// member whose fields are accessed as if they belonged to the parent.
+    pub name: Option<String>,
+    pub type_: String,
+    #[serde(flatten)]
+    pub info: TypeInfo,
+    /// The alignment explicitly requested with `alignas`, `_Alignas` or
+    /// `__attribute__((aligned))`, as opposed to the natural alignment of
+    /// the type.
+    pub requested_alignment: Option<usize>,
+    /// Whether this is the member that determines the size of the union.
+    #[serde(default)]
+    pub is_largest: bool,
+}
+
+/// A global variable declared in a header.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct VariableType {
+    pub name: String,
+    /// Where the type is declared, if locations were requested.
+    pub location: Option<Location>,
+    pub type_: String,
+    #[serde(flatten)]
+    pub info: TypeInfo,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct FunctionType {
+    pub name: String,
+    /// Where the type is declared, if locations were requested.
+    pub location: Option<Location>,
+    #[serde(flatten)]
+    pub signature: FunctionSignature,
+}
+
+/// A compile-time integer constant, such as a `static const` member of a C++
+/// record, named `record::member`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct ConstantType {
+    pub name: String,
+    /// Where the constant is declared, if locations were requested.
+    pub location: Option<Location>,
+    pub type_: String,
+    #[serde(flatten)]
+    pub info: TypeInfo,
+    pub value: i64,
+}
+
+/// The return and parameter types of a function or function pointer.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct FunctionSignature {
+    pub return_type: String,
+    pub return_info: TypeInfo,
+    pub parameters: Vec<Parameter>,
+    pub is_variadic: bool,
+    /// Whether the parameters are left unspecified, as in C's `int f()`, in
+    /// which case `parameters` is empty but the function may be called with
+    /// any arguments. `int f(void)` takes no arguments at all.
+    #[serde(default)]
+    pub has_unspecified_parameters: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Parameter {
+    /// The name of the parameter, which may be left out in a declaration.
+    pub name: Option<String>,
+    pub type_: String,
+    #[serde(flatten)]
+    pub info: TypeInfo,
+}
+
+/// What a type is once typedefs, `typeof` and other sugar are looked through.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum TypeKind {
+    Primitive,
+    Pointer,
+    Array,
+    Record,
+    Enum,
+    Function,
+    #[default]
+    Other,
+}
+
+/// Structured information about the type of a field, variable, parameter or
+/// the type a typedef is an alias for.
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypeInfo {
+    pub type_kind: TypeKind,
+    /// The spelling of the canonical type, e.g. `unsigned __int128` for a
+    /// field declared as `__uint128_t`.
+    pub canonical: String,
+    pub size: Option<usize>,
+    pub alignment: Option<usize>,
+    /// The number of pointers that have to be followed to get to the value,
+    /// e.g. 2 for `char **`. For arrays, this and the following fields are
+    /// about their elements.
+    pub pointer_depth: usize,
+    /// Whether the value, after following all pointers, is const, e.g. true
+    /// for both `const int` and `const char *`.
+    pub is_const: bool,
+    /// The signature of the function the pointers lead to, for function
+    /// pointers like `int (*)(const void *, const void *)` or arrays of them
+    /// like `int (*[4])(int)`.
+    pub function: Option<Box<FunctionSignature>>,
+    /// The evaluated length of every dimension of an array, outermost first,
+    /// e.g. `[2, 3]` for `int [2][3]`. Flexible array members have none.
+    pub array_sizes: Vec<usize>,
+    /// The element type of an array, e.g. `float` for `float [4][4]`.
+    pub array_element: Option<String>,
+    /// How the length of every dimension of an array is spelled in the
+    /// header, e.g. `["MAX_LEN"]`, if requested with
+    /// `ParseOptions::array_size_spellings`.
+    pub array_size_spellings: Option<Vec<String>>,
+}
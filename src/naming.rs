@@ -0,0 +1,128 @@
+use crate::UnknownValue;
+use std::str::FromStr;
+
+/// How names are synthesized for anonymous types, given as a template in
+/// which the following placeholders are replaced:
+///
+/// * `{parent}`: the name of the enclosing type, `{Parent}` capitalized
+/// * `{field}`: the name of the field the type is declared for, or its index
+///   if there is none, `{Field}` capitalized
+/// * `{index}`: the position of the type within the enclosing type
+/// * `{counter}`: the number of anonymous types named before this one
+///
+/// Names only depend on the header, so they are stable between runs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AnonNaming {
+    template: String,
+}
+
+/// Named templates that can be given instead of a template.
+const PRESETS: &[(&str, &str)] = &[
+    ("qualified", "{parent}::{field}"),
+    ("index", "{parent}::{index}"),
+    ("counter", "__anon_{counter}"),
+    ("camel", "{Parent}{Field}_t"),
+];
+
+impl AnonNaming {
+    pub fn new<S: Into<String>>(template: S) -> Self {
+        AnonNaming {
+            template: template.into(),
+        }
+    }
+
+    pub(crate) fn name(
+        &self,
+        parent: &str,
+        field: Option<&str>,
+        index: usize,
+        counter: usize,
+    ) -> String {
+        let index = index.to_string();
+        let field = field.unwrap_or(&index);
+        self.template
+            .replace("{parent}", parent)
+            .replace("{Parent}", &capitalize(parent))
+            .replace("{field}", field)
+            .replace("{Field}", &capitalize(field))
+            .replace("{index}", &index)
+            .replace("{counter}", &counter.to_string())
+    }
+}
+
+impl Default for AnonNaming {
+    fn default() -> Self {
+        AnonNaming::new(PRESETS[0].1)
+    }
+}
+
+impl FromStr for AnonNaming {
+    type Err = UnknownValue;
+
+    fn from_str(s: &str) -> Result<Self, UnknownValue> {
+        if let Some(&(_, template)) = PRESETS.iter().find(|&&(name, _)| name == s) {
+            return Ok(AnonNaming::new(template));
+        }
+        if !s.contains('{') {
+            let presets: Vec<&str> = PRESETS.iter().map(|&(name, _)| name).collect();
+            return Err(UnknownValue {
+                option: "naming",
+                value: s.to_string(),
+                expected: format!("a template or one of: {}", presets.join(", ")),
+            });
+        }
+        Ok(AnonNaming::new(s))
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qualified_names_after_parent_and_field() {
+        let naming = AnonNaming::default();
+        assert_eq!(naming.name("foo", Some("kind"), 1, 0), "foo::kind");
+        // Without a field, e.g. for anonymous members, the index is used.
+        assert_eq!(naming.name("foo", None, 1, 0), "foo::1");
+    }
+
+    #[test]
+    fn presets() {
+        let name = |preset: &str| {
+            preset
+                .parse::<AnonNaming>()
+                .unwrap()
+                .name("foo", Some("kind"), 2, 3)
+        };
+        assert_eq!(name("qualified"), "foo::kind");
+        assert_eq!(name("index"), "foo::2");
+        assert_eq!(name("counter"), "__anon_3");
+        assert_eq!(name("camel"), "FooKind_t");
+    }
+
+    #[test]
+    fn templates() {
+        let naming: AnonNaming = "{parent}_{field}_{index}".parse().unwrap();
+        assert_eq!(naming.name("foo", Some("kind"), 2, 0), "foo_kind_2");
+    }
+
+    #[test]
+    fn unknown_preset() {
+        let e = "bogus".parse::<AnonNaming>().unwrap_err();
+        assert_eq!(e.value, "bogus");
+        assert_eq!(
+            e.to_string(),
+            "unknown naming `bogus`, expected a template or one of: qualified, index, counter, \
+             camel"
+        );
+    }
+}
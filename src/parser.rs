@@ -0,0 +1,1429 @@
+//! Parsing headers with clang into the types of `crate::model`.
+
+use crate::naming::AnonNaming;
+use crate::{
+    ConstantType, EnumField, EnumType, FunctionSignature, FunctionType, Location, Parameter,
+    ParseError, StructField, StructType, TypeDefType, TypeInfo, TypeKind, Types, UnionField,
+    UnionType, VariableType,
+};
+use clang::diagnostic::Severity;
+use clang::token::TokenKind;
+use clang::{
+    Clang, Entity, EntityKind, EntityVisitResult, EvaluationResult, Index, TranslationUnit, Type,
+};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// What to do about types that are skipped because they have no name, such
+/// as an anonymous enum that is not part of any record or typedef.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnSkip {
+    Ignore,
+    /// Report a warning with the location of every skipped type.
+    Warn,
+    /// Fail with the location of every skipped type.
+    Fail,
+}
+
+/// The order in which types nested in records are output relative to the
+/// other types.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Order {
+    /// Output nested types as they are encountered, right before the record
+    /// they are nested in.
+    DepthFirst,
+    /// Output all top-level types first, then the types nested in them, then
+    /// the types nested in those and so on.
+    BreadthFirst,
+}
+
+/// A diagnostic reported by clang while parsing a header.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub is_error: bool,
+}
+
+/// Something in a header that could not be represented faithfully, reported
+/// by `ParseOptions::parse_with_warnings`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Warning {
+    /// An unnamed type was left out while `OnSkip::Warn` was in effect.
+    SkippedUnnamed {
+        kind: &'static str,
+        location: String,
+    },
+    /// A typedef was left out as libclang has no underlying type for it, e.g.
+    /// for dependent types in C++ templates or undeclared types.
+    NoUnderlyingType { typedef: String },
+    /// A constant was left out as its value does not fit in an i64.
+    ConstantOutOfRange { constant: String, value: u64 },
+    /// The value of an enum constant does not fit in an i64 and is stored
+    /// as the i64 with the same bits.
+    EnumValueOutOfRange {
+        constant: String,
+        value: u64,
+        stored: i64,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::SkippedUnnamed { kind, location } => {
+                write!(f, "skipping unnamed {} at {}", kind, location)
+            }
+            Warning::NoUnderlyingType { typedef } => {
+                write!(f, "skipping typedef `{}` with no underlying type", typedef)
+            }
+            Warning::ConstantOutOfRange { constant, value } => write!(
+                f,
+                "skipping constant `{}` as {} does not fit in an i64",
+                constant, value
+            ),
+            Warning::EnumValueOutOfRange {
+                constant,
+                value,
+                stored,
+            } => write!(
+                f,
+                "value {} of `{}` does not fit in an i64 and is stored as {}",
+                value, constant, stored
+            ),
+        }
+    }
+}
+
+/// What to do with an entity, as decided by a hook set with
+/// `ParseOptions::on_entity`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EntityHandling {
+    /// Parse the entity as if there was no hook.
+    Default,
+    /// Leave the entity out.
+    Skip,
+    /// Output the given types instead of parsing the entity, e.g. to handle
+    /// a kind of entity that is not supported.
+    Replace(Vec<Types>),
+}
+
+/// The error returned by `Clang::new` while another instance exists.
+const CLANG_IN_USE: &str = "an instance of `Clang` already exists";
+
+/// Why libclang failed to load, if it did. `Clang::new` reports any later
+/// attempt as `CLANG_IN_USE` instead, so this error is repeated for those.
+static LOAD_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+type HookFn = dyn for<'tu> Fn(&Entity<'tu>) -> EntityHandling + Send + Sync;
+
+#[derive(Clone)]
+struct Hook(Arc<HookFn>);
+
+impl fmt::Debug for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Hook")
+    }
+}
+
+/// Configuration for parsing a header.
+///
+/// ```no_run
+/// use ctypeparser::ParseOptions;
+///
+/// let types = ParseOptions::new()
+///     .target("x86_64-pc-windows-msvc")
+///     .clang_args(&["-DUNICODE"])
+///     .parse("windows.h")?;
+/// # Ok::<(), ctypeparser::ParseError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    target: Option<String>,
+    resource_dir: Option<PathBuf>,
+    clang_args: Vec<String>,
+    include_system: bool,
+    follow_definitions: bool,
+    anon_naming: AnonNaming,
+    on_skip: OnSkip,
+    with_locations: bool,
+    relative_to: Option<PathBuf>,
+    main_files: Vec<PathBuf>,
+    hex_values: bool,
+    array_size_spellings: bool,
+    order: Order,
+    hook: Option<Hook>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            target: None,
+            resource_dir: None,
+            clang_args: Vec::new(),
+            include_system: false,
+            follow_definitions: true,
+            anon_naming: AnonNaming::default(),
+            on_skip: OnSkip::Ignore,
+            with_locations: false,
+            relative_to: None,
+            main_files: Vec::new(),
+            hex_values: false,
+            array_size_spellings: false,
+            order: Order::DepthFirst,
+            hook: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the target triple to parse the header for.
+    pub fn target<S: Into<String>>(mut self, target: S) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Sets the directory clang looks for its builtin headers such as
+    /// `stddef.h` in, for when libclang is not installed along with them.
+    /// A `-resource-dir` in the clang arguments takes precedence.
+    pub fn resource_dir<P: Into<PathBuf>>(mut self, resource_dir: P) -> Self {
+        self.resource_dir = Some(resource_dir.into());
+        self
+    }
+
+    /// Adds extra arguments that are passed to clang as-is.
+    pub fn clang_args<S: AsRef<str>>(mut self, args: &[S]) -> Self {
+        self.clang_args
+            .extend(args.iter().map(|a| a.as_ref().to_string()));
+        self
+    }
+
+    /// Sets whether types declared outside of the main file, e.g. in system
+    /// headers, are included in the output.
+    pub fn include_system(mut self, include_system: bool) -> Self {
+        self.include_system = include_system;
+        self
+    }
+
+    /// Adds headers whose types are kept as if they were declared in the
+    /// parsed header, e.g. public headers that it includes. Types declared in
+    /// other included headers are still left out.
+    pub fn main_files<P: AsRef<Path>>(mut self, files: &[P]) -> Self {
+        self.main_files
+            .extend(files.iter().map(|f| f.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Sets whether declarations are replaced by their definition when one
+    /// exists. This handles the case of forward declarations.
+    pub fn follow_definitions(mut self, follow_definitions: bool) -> Self {
+        self.follow_definitions = follow_definitions;
+        self
+    }
+
+    /// Sets how anonymous types nested in records are named.
+    pub fn anon_naming(mut self, anon_naming: AnonNaming) -> Self {
+        self.anon_naming = anon_naming;
+        self
+    }
+
+    /// Sets what to do about types that are skipped because they have no
+    /// name.
+    pub fn on_skip(mut self, on_skip: OnSkip) -> Self {
+        self.on_skip = on_skip;
+        self
+    }
+
+    /// Sets whether the values of enum constants are also given in
+    /// hexadecimal, which is easier to read for flags.
+    pub fn hex_values(mut self, hex_values: bool) -> Self {
+        self.hex_values = hex_values;
+        self
+    }
+
+    /// Sets whether the lengths of arrays are also given as spelled in the
+    /// header, e.g. `MAX_LEN` rather than `16`.
+    pub fn array_size_spellings(mut self, array_size_spellings: bool) -> Self {
+        self.array_size_spellings = array_size_spellings;
+        self
+    }
+
+    /// Sets the order in which nested types are output.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets whether the location of every type is recorded.
+    pub fn with_locations(mut self, with_locations: bool) -> Self {
+        self.with_locations = with_locations;
+        self
+    }
+
+    /// Sets a directory that the paths of recorded locations are made
+    /// relative to, so that the output does not depend on where the headers
+    /// are checked out.
+    pub fn relative_to<P: Into<PathBuf>>(mut self, base: P) -> Self {
+        self.relative_to = Some(base.into());
+        self
+    }
+
+    /// Sets a hook that is called with every top-level entity that would be
+    /// parsed, after definitions are followed and entities outside of the
+    /// main file are filtered out, and decides how it is handled.
+    ///
+    /// ```no_run
+    /// use ctypeparser::clang::EntityKind;
+    /// use ctypeparser::{EntityHandling, ParseOptions};
+    ///
+    /// // Leave out private functions.
+    /// let types = ParseOptions::new()
+    ///     .on_entity(|entity| {
+    ///         let name = entity.get_name().unwrap_or_default();
+    ///         if entity.get_kind() == EntityKind::FunctionDecl && name.starts_with('_') {
+    ///             EntityHandling::Skip
+    ///         } else {
+    ///             EntityHandling::Default
+    ///         }
+    ///     })
+    ///     .parse("api.h")?;
+    /// # Ok::<(), ctypeparser::ParseError>(())
+    /// ```
+    pub fn on_entity<F>(mut self, hook: F) -> Self
+    where
+        F: for<'tu> Fn(&Entity<'tu>) -> EntityHandling + Send + Sync + 'static,
+    {
+        self.hook = Some(Hook(Arc::new(hook)));
+        self
+    }
+
+    /// Parses the header at `path` and returns the types declared in it.
+    /// Warnings are dropped, see `parse_with_warnings`.
+    pub fn parse<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Types>, ParseError> {
+        self.parse_with_warnings(path).map(|(types, _)| types)
+    }
+
+    /// Parses the header at `path` and returns the types declared in it,
+    /// along with warnings about what could not be represented faithfully.
+    pub fn parse_with_warnings<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<(Vec<Types>, Vec<Warning>), ParseError> {
+        self.with_translation_unit(path.as_ref(), |translation_unit| {
+            self.visit(path.as_ref(), translation_unit)
+        })?
+    }
+
+    /// Parses the header at `path` and returns the diagnostics clang reported
+    /// for it, without extracting any types.
+    pub fn check<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Diagnostic>, ParseError> {
+        self.with_translation_unit(path.as_ref(), |translation_unit| {
+            translation_unit
+                .get_diagnostics()
+                .iter()
+                .map(|diagnostic| Diagnostic {
+                    message: diagnostic.to_string(),
+                    is_error: diagnostic.get_severity() >= Severity::Error,
+                })
+                .collect()
+        })
+    }
+
+    /// Returns the header at `path` along with every header it includes that
+    /// declares something, excluding system headers.
+    pub fn dependencies<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>, ParseError> {
+        self.with_translation_unit(path.as_ref(), |translation_unit| {
+            let canonicalize = |path: PathBuf| path.canonicalize().unwrap_or(path);
+
+            let mut files = BTreeSet::new();
+            files.insert(canonicalize(path.as_ref().to_path_buf()));
+            for entity in translation_unit.get_entity().get_children() {
+                if entity.is_in_system_header() {
+                    continue;
+                }
+                let file = entity
+                    .get_location()
+                    .and_then(|location| location.get_file_location().file);
+                if let Some(file) = file {
+                    files.insert(canonicalize(file.get_path()));
+                }
+            }
+            files.into_iter().collect()
+        })
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        let mut arguments = Vec::new();
+        if let Some(target) = &self.target {
+            arguments.push(format!("--target={}", target));
+        }
+        if let Some(resource_dir) = &self.resource_dir {
+            arguments.push("-resource-dir".to_string());
+            arguments.push(resource_dir.display().to_string());
+        }
+        arguments.extend(self.clang_args.iter().cloned());
+        arguments
+    }
+
+    fn with_translation_unit<T, F>(&self, path: &Path, f: F) -> Result<T, ParseError>
+    where
+        F: FnOnce(&TranslationUnit) -> T,
+    {
+        // Clang reports a missing header as a generic failure.
+        if !path.exists() {
+            return Err(ParseError::FileNotFound(path.to_path_buf()));
+        }
+
+        let clang = Clang::new().map_err(|message| {
+            let mut load_error = LOAD_ERROR.lock().unwrap_or_else(PoisonError::into_inner);
+            // This is the only error when libclang is linked to.
+            if message != CLANG_IN_USE && cfg!(feature = "runtime") {
+                *load_error = Some(message);
+            }
+            match &*load_error {
+                Some(message) => ParseError::ClangInit(message.clone()),
+                None => ParseError::ClangInUse,
+            }
+        })?;
+        let index = Index::new(&clang, true, true);
+        let translation_unit = index
+            .parser(path)
+            .arguments(&self.arguments())
+            .parse()
+            .map_err(|e| ParseError::TranslationUnit {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })?;
+        Ok(f(&translation_unit))
+    }
+
+    fn visit(
+        &self,
+        path: &Path,
+        translation_unit: &TranslationUnit,
+    ) -> Result<(Vec<Types>, Vec<Warning>), ParseError> {
+        let mut context = Context {
+            anon_naming: self.anon_naming.clone(),
+            with_locations: self.with_locations,
+            hex_values: self.hex_values,
+            array_size_spellings: self.array_size_spellings,
+            relative_to: self
+                .relative_to
+                .as_ref()
+                .map(|base| base.canonicalize().unwrap_or_else(|_| base.clone())),
+            ..Context::default()
+        };
+        let entity = translation_unit.get_entity();
+        // Clang only considers the first inclusion of the header to be in the
+        // main file, so sections of an amalgamated header that includes itself
+        // are matched by the file instead.
+        let main_files: HashSet<clang::source::File> = std::iter::once(path)
+            .chain(self.main_files.iter().map(PathBuf::as_path))
+            .filter_map(|file| translation_unit.get_file(file))
+            .collect();
+        let is_in_main_file = |entity: &Entity| {
+            let file = entity
+                .get_location()
+                .and_then(|location| location.get_file_location().file);
+            entity.is_in_main_file() || matches!(file, Some(file) if main_files.contains(&file))
+        };
+
+        let mut seen = HashSet::new();
+        let _ = entity.visit_children(|entity, parent| -> EntityVisitResult {
+            let e = if self.follow_definitions {
+                entity.get_definition().unwrap_or(entity)
+            } else {
+                entity
+            };
+
+            if !self.include_system && !is_in_main_file(&e) {
+                return EntityVisitResult::Continue;
+            }
+
+            // Redeclarations, e.g. from including a header more than once
+            // without include guards, would otherwise be output again. They
+            // are only distinct when declarations are kept as they are.
+            let key = if self.follow_definitions {
+                e.get_canonical_entity()
+            } else {
+                e
+            };
+            if !seen.insert(key) {
+                return EntityVisitResult::Continue;
+            }
+
+            if let Some(Hook(hook)) = &self.hook {
+                match hook(&e) {
+                    EntityHandling::Default => {}
+                    EntityHandling::Skip => return EntityVisitResult::Continue,
+                    EntityHandling::Replace(types) => {
+                        for type_ in types {
+                            context.push(&e, type_);
+                        }
+                        return EntityVisitResult::Continue;
+                    }
+                }
+            }
+
+            match e.get_kind() {
+                EntityKind::TypedefDecl => parse_typedef(&e, &mut context),
+                EntityKind::VarDecl => parse_variable(&e, &mut context),
+                EntityKind::FunctionDecl => parse_function(&e, &mut context),
+                EntityKind::ObjCInterfaceDecl => {
+                    parse_declaration(&e, e.get_name().unwrap(), &mut context)
+                }
+                EntityKind::StructDecl
+                | EntityKind::ClassDecl
+                | EntityKind::EnumDecl
+                | EntityKind::UnionDecl => {
+                    let name = get_name(&e, &parent)
+                        .or_else(|| variable_type_name(&e, &parent, &mut context));
+                    match name {
+                        Some(name) => parse_declaration(&e, name, &mut context),
+                        None => context.skipped.push(e),
+                    }
+                }
+                _ => {}
+            };
+            EntityVisitResult::Continue
+        });
+
+        match self.on_skip {
+            OnSkip::Ignore => {}
+            OnSkip::Warn => {
+                for entity in &context.skipped {
+                    context.warnings.push(Warning::SkippedUnnamed {
+                        kind: kind_name(entity),
+                        location: location(entity),
+                    });
+                }
+            }
+            OnSkip::Fail => {
+                if !context.skipped.is_empty() {
+                    let locations = context.skipped.iter().map(location).collect();
+                    return Err(ParseError::UnnamedEntity(locations));
+                }
+            }
+        }
+
+        if self.order == Order::BreadthFirst {
+            let mut types: Vec<(usize, Types)> =
+                context.depths.into_iter().zip(context.types).collect();
+            // The sort is stable, keeping types of the same depth in order.
+            types.sort_by_key(|&(depth, _)| depth);
+            let types = types.into_iter().map(|(_, type_)| type_).collect();
+            return Ok((types, context.warnings));
+        }
+        Ok((context.types, context.warnings))
+    }
+}
+
+/// State shared between the parsers of individual entities.
+#[derive(Default)]
+struct Context<'tu> {
+    types: Vec<Types>,
+    /// Names given to anonymous types nested in records or declared for a
+    /// variable, keyed by the canonical entity of their declaration.
+    anonymous: HashMap<Entity<'tu>, String>,
+    anon_naming: AnonNaming,
+    /// Types that could not be named and were left out of `types`.
+    skipped: Vec<Entity<'tu>>,
+    with_locations: bool,
+    hex_values: bool,
+    array_size_spellings: bool,
+    relative_to: Option<PathBuf>,
+    /// How deeply nested in records the types currently being parsed are.
+    depth: usize,
+    /// The depth of every type in `types`.
+    depths: Vec<usize>,
+    warnings: Vec<Warning>,
+}
+
+impl<'tu> Context<'tu> {
+    /// Adds a type declared by `entity`, recording its location if requested.
+    fn push(&mut self, entity: &Entity, mut type_: Types) {
+        if self.with_locations {
+            *type_.location_mut() = self.location(entity);
+        }
+        self.types.push(type_);
+        self.depths.push(self.depth);
+    }
+
+    fn location(&self, entity: &Entity) -> Option<Location> {
+        let location = entity.get_location()?.get_file_location();
+        let path = location.file?.get_path();
+        // Paths outside of the base are left absolute.
+        let path = match &self.relative_to {
+            Some(base) => {
+                let path = path.canonicalize().unwrap_or(path);
+                match path.strip_prefix(base) {
+                    Ok(relative) => relative.to_path_buf(),
+                    Err(_) => path,
+                }
+            }
+            None => path,
+        };
+        Some(Location {
+            file: path.display().to_string(),
+            line: location.line,
+            column: location.column,
+        })
+    }
+}
+
+fn kind_name(entity: &Entity) -> &'static str {
+    match entity.get_kind() {
+        EntityKind::StructDecl => "struct",
+        EntityKind::ClassDecl => "class",
+        EntityKind::EnumDecl => "enum",
+        EntityKind::UnionDecl => "union",
+        _ => "declaration",
+    }
+}
+
+/// Formats the location of `entity` as `file:line:column`.
+fn location(entity: &Entity) -> String {
+    match entity.get_location().map(|l| l.get_file_location()) {
+        Some(clang::source::Location {
+            file: Some(file),
+            line,
+            column,
+            ..
+        }) => format!("{}:{}:{}", file.get_path().display(), line, column),
+        _ => "<unknown location>".to_string(),
+    }
+}
+
+fn parse_declaration<'tu>(entity: &Entity<'tu>, name: String, context: &mut Context<'tu>) {
+    match entity.get_kind() {
+        // C++ classes only differ from structs in the default access of
+        // their members.
+        EntityKind::StructDecl | EntityKind::ClassDecl => parse_struct(entity, name, context),
+        EntityKind::EnumDecl => parse_enum(entity, name, context),
+        EntityKind::UnionDecl => parse_union(entity, name, context),
+        // Only the instance variables of Objective-C classes are parsed, as
+        // they make up their layout.
+        EntityKind::ObjCInterfaceDecl => parse_struct(entity, name, context),
+        _ => {}
+    }
+}
+
+fn parse_typedef(entity: &Entity, context: &mut Context) {
+    let name = entity.get_name().unwrap();
+    // libclang has no underlying type for some declarations it failed to
+    // make sense of, e.g. typedefs of dependent types in C++ templates, and
+    // substitutes `int` for that of invalid ones, e.g. of undeclared types.
+    let underlying = entity
+        .get_typedef_underlying_type()
+        .filter(|_| !entity.is_invalid_declaration());
+    let underlying = match underlying {
+        Some(underlying) => underlying,
+        None => {
+            context
+                .warnings
+                .push(Warning::NoUnderlyingType { typedef: name });
+            return;
+        }
+    };
+    context.push(
+        entity,
+        Types::TypeDef(TypeDefType {
+            name,
+            location: None,
+            underlying: field_type_name(&underlying, context),
+            info: get_type_info(&underlying),
+            resolved_chain: typedef_chain(&underlying, context),
+        }),
+    )
+}
+
+/// Follows `type_` through any typedefs it refers to, returning the name of
+/// every type along the way.
+fn typedef_chain(type_: &Type, context: &Context) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut type_ = *type_;
+    loop {
+        // References to a typedef may be wrapped in an elaborated type, e.g.
+        // when qualified with a namespace.
+        if let Some(named) = type_.get_elaborated_type() {
+            type_ = named;
+        }
+        chain.push(field_type_name(&type_, context));
+        if type_.get_kind() != clang::TypeKind::Typedef {
+            break;
+        }
+        match type_
+            .get_declaration()
+            .and_then(|declaration| declaration.get_typedef_underlying_type())
+        {
+            Some(underlying) => type_ = underlying,
+            None => break,
+        }
+    }
+    chain
+}
+
+fn get_name(entity: &Entity, parent: &Entity) -> Option<String> {
+    declared_name(entity)
+        .or_else(|| find_declarator(entity, parent, EntityKind::TypedefDecl)?.get_name())
+}
+
+/// Returns the name an entity was declared with. Newer versions of libclang
+/// spell anonymous records and enums as e.g. `struct (unnamed at foo.h:1:9)`,
+/// which is not a name we want to emit.
+fn declared_name(entity: &Entity) -> Option<String> {
+    entity
+        .get_name()
+        .filter(|name| !name.contains("(unnamed") && !name.contains("(anonymous"))
+}
+
+/// Returns the first typedef, variable or field of the given kind declared
+/// alongside the anonymous `entity` with it as its type, e.g. `a_t` in
+/// `typedef struct { int x; } a_t, b_t;`. Declarators such as `*p_t` that
+/// do not have the entity itself as their type are skipped.
+fn find_declarator<'tu>(
+    entity: &Entity<'tu>,
+    parent: &Entity<'tu>,
+    kind: EntityKind,
+) -> Option<Entity<'tu>> {
+    let canonical = entity.get_canonical_entity();
+    parent
+        .get_children()
+        .into_iter()
+        .filter(|sibling| sibling.get_kind() == kind)
+        .find(|sibling| {
+            let type_ = match kind {
+                EntityKind::TypedefDecl => sibling.get_typedef_underlying_type(),
+                _ => sibling.get_type(),
+            };
+            type_
+                .and_then(|type_| type_.get_declaration())
+                .map(|declaration| declaration.get_canonical_entity())
+                == Some(canonical)
+        })
+}
+
+/// Names an anonymous type that only exists as the type of a variable, e.g.
+/// `struct { int x; } global;`. It has no enclosing type, so it is named as
+/// if it was a field called `global` of a type without a name.
+fn variable_type_name<'tu>(
+    entity: &Entity<'tu>,
+    parent: &Entity<'tu>,
+    context: &mut Context<'tu>,
+) -> Option<String> {
+    let variable = find_declarator(entity, parent, EntityKind::VarDecl)?;
+    let index = parent
+        .get_children()
+        .iter()
+        .position(|sibling| sibling == entity)
+        .unwrap_or_default();
+    let name = context.anon_naming.name(
+        "",
+        variable.get_name().as_deref(),
+        index,
+        context.anonymous.len(),
+    );
+    context
+        .anonymous
+        .insert(entity.get_canonical_entity(), name.clone());
+    Some(name)
+}
+
+/// Parses the enums, structs and unions declared inside the body of the
+/// record `entity`. Anonymous ones are named according to the configured
+/// `AnonNaming`, by default after the record and the field they are the type
+/// of, e.g. `foo::kind` for `struct foo { enum { A, B } kind; };`.
+fn parse_nested<'tu>(entity: &Entity<'tu>, name: &str, context: &mut Context<'tu>) {
+    let children = entity.get_children();
+    for (i, child) in children.iter().enumerate() {
+        match child.get_kind() {
+            EntityKind::StructDecl
+            | EntityKind::ClassDecl
+            | EntityKind::EnumDecl
+            | EntityKind::UnionDecl => {}
+            _ => continue,
+        }
+
+        let nested_name = match declared_name(child) {
+            Some(nested_name) => nested_name,
+            None => {
+                let field = find_declarator(child, entity, EntityKind::FieldDecl)
+                    .and_then(|field| field.get_name());
+                let nested_name =
+                    context
+                        .anon_naming
+                        .name(name, field.as_deref(), i, context.anonymous.len());
+                context
+                    .anonymous
+                    .insert(child.get_canonical_entity(), nested_name.clone());
+                nested_name
+            }
+        };
+        context.depth += 1;
+        parse_declaration(child, nested_name, context);
+        context.depth -= 1;
+    }
+}
+
+/// Parses the `static const` integer members of a C++ record. Those are
+/// declared as variables rather than fields, and other static members are
+/// left out like any variable that is not at file scope.
+fn parse_constants(entity: &Entity, name: &str, context: &mut Context) {
+    for member in entity.get_children() {
+        if member.get_kind() != EntityKind::VarDecl {
+            continue;
+        }
+        let type_ = match member.get_type() {
+            Some(type_) if type_.is_const_qualified() => type_,
+            _ => continue,
+        };
+        let value = match member.evaluate() {
+            Some(EvaluationResult::SignedInteger(value)) => value,
+            Some(EvaluationResult::UnsignedInteger(value)) => match i64::try_from(value) {
+                Ok(value) => value,
+                Err(_) => {
+                    context.warnings.push(Warning::ConstantOutOfRange {
+                        constant: format!("{}::{}", name, member.get_name().unwrap()),
+                        value,
+                    });
+                    continue;
+                }
+            },
+            _ => continue,
+        };
+
+        let constant = Types::Constant(ConstantType {
+            name: format!("{}::{}", name, member.get_name().unwrap()),
+            location: None,
+            type_: field_type_name(&type_, context),
+            info: get_type_info(&type_),
+            value,
+        });
+        context.depth += 1;
+        context.push(&member, constant);
+        context.depth -= 1;
+    }
+}
+
+/// Returns the name of the type of a field or variable, which for anonymous
+/// types is the name given to them by `parse_nested` or
+/// `variable_type_name`.
+fn field_type_name(type_: &Type, context: &Context) -> String {
+    type_
+        .get_declaration()
+        .and_then(|declaration| context.anonymous.get(&declaration.get_canonical_entity()))
+        .cloned()
+        .unwrap_or_else(|| display_name(type_))
+}
+
+/// Returns the fields of the record or Objective-C class declared by
+/// `entity`. Those of records are listed by their type, as anonymous struct
+/// and union members such as `union { int i; float f; };` are declared by
+/// implicit fields, which are not among the children of the record.
+fn get_fields<'tu>(entity: &Entity<'tu>) -> Vec<Entity<'tu>> {
+    if entity.get_kind() == EntityKind::ObjCInterfaceDecl {
+        return entity
+            .get_children()
+            .into_iter()
+            .filter(|child| child.get_kind() == EntityKind::ObjCIvarDecl)
+            .collect();
+    }
+    // The type of a forward declaration is that of the definition, but its
+    // fields are not part of the declaration.
+    if !entity.is_definition() {
+        return Vec::new();
+    }
+    entity
+        .get_type()
+        .and_then(|type_| type_.get_canonical_type().get_fields())
+        .unwrap_or_default()
+}
+
+fn parse_variable(entity: &Entity, context: &mut Context) {
+    let type_ = entity.get_type().unwrap();
+    context.push(
+        entity,
+        Types::Variable(VariableType {
+            name: entity.get_name().unwrap(),
+            location: None,
+            type_: field_type_name(&type_, context),
+            info: get_declared_type_info(entity, &type_, context),
+        }),
+    );
+}
+
+fn parse_function(entity: &Entity, context: &mut Context) {
+    let return_type = entity.get_result_type().unwrap();
+    let parameters = entity
+        .get_arguments()
+        .unwrap_or_default()
+        .iter()
+        .map(|parameter| {
+            let type_ = parameter.get_type().unwrap();
+            Parameter {
+                name: parameter.get_name(),
+                type_: field_type_name(&type_, context),
+                info: get_type_info(&type_),
+            }
+        })
+        .collect();
+
+    context.push(
+        entity,
+        Types::Function(FunctionType {
+            name: entity.get_name().unwrap(),
+            location: None,
+            signature: FunctionSignature {
+                return_type: field_type_name(&return_type, context),
+                return_info: get_type_info(&return_type),
+                parameters,
+                is_variadic: entity.is_variadic(),
+                has_unspecified_parameters: entity
+                    .get_type()
+                    .map(|type_| has_unspecified_parameters(&type_))
+                    == Some(true),
+            },
+        }),
+    );
+}
+
+fn parse_struct<'tu>(entity: &Entity<'tu>, name: String, context: &mut Context<'tu>) {
+    parse_nested(entity, &name, context);
+    parse_constants(entity, &name, context);
+
+    let fields: Vec<StructField> = get_fields(entity)
+        .iter()
+        .map(|field| {
+            let type_ = field.get_type().unwrap();
+            let info = get_declared_type_info(field, &type_, context);
+            StructField {
+                name: declared_name(field),
+                type_: field_type_name(&type_, context),
+                offset: field.get_offset_of_field().ok().map(|bits| bits / 8),
+                is_atomic: is_atomic(&type_),
+                info,
+                requested_alignment: requested_alignment(field),
+                bit_width: field.get_bit_field_width(),
+                bit_offset: field
+                    .get_bit_field_width()
+                    .and_then(|_| field.get_offset_of_field().ok()),
+            }
+        })
+        .collect();
+
+    let (size, alignment) = get_layout(entity);
+    context.push(
+        entity,
+        Types::Struct(StructType {
+            name,
+            location: None,
+            is_definition: entity.is_definition(),
+            size,
+            alignment,
+            fields_size: size.map(|_| fields_size(entity)),
+            requested_alignment: requested_alignment(entity),
+            is_packed: is_packed(entity, alignment),
+            fields,
+        }),
+    );
+}
+
+fn parse_enum(entity: &Entity, name: String, context: &mut Context) {
+    let is_unsigned = entity
+        .get_enum_underlying_type()
+        .map(|underlying| is_unsigned(&underlying))
+        == Some(true);
+    let mut warnings = Vec::new();
+    // Attributes such as `__attribute__((packed))` are children of the enum
+    // as well.
+    let fields: Vec<EnumField> = entity
+        .get_children()
+        .iter()
+        .filter(|child| child.get_kind() == EntityKind::EnumConstantDecl)
+        .map(|field| {
+            // libclang sign-extends the value regardless of the underlying
+            // type, so the values of unsigned enums are taken from the
+            // zero-extended one instead. Only those of 64-bit enums may not
+            // fit in an i64.
+            let (signed, unsigned) = field.get_enum_constant_value().unwrap();
+            let value = if !is_unsigned {
+                signed
+            } else {
+                match i64::try_from(unsigned) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        warnings.push(Warning::EnumValueOutOfRange {
+                            constant: format!("{}::{}", name, field.get_name().unwrap()),
+                            value: unsigned,
+                            stored: signed,
+                        });
+                        signed
+                    }
+                }
+            };
+            EnumField {
+                name: field.get_name().unwrap(),
+                value,
+                is_out_of_range: is_out_of_range(field, value, unsigned),
+                value_hex: if !context.hex_values {
+                    None
+                } else if value < 0 && !is_unsigned {
+                    Some(format!("-{:#x}", value.unsigned_abs()))
+                } else {
+                    Some(format!("{:#x}", unsigned))
+                },
+            }
+        })
+        .collect();
+    context.warnings.extend(warnings);
+
+    context.push(
+        entity,
+        Types::Enum(EnumType {
+            name,
+            location: None,
+            is_definition: entity.is_definition(),
+            underlying: entity
+                .get_enum_underlying_type()
+                .map(|underlying| display_name(&underlying)),
+            fields,
+        }),
+    );
+}
+
+/// Returns whether `type_` is atomic, which libclang only exposes through the
+/// spelling of the canonical type, e.g. `_Atomic(long)`. Pointers to atomic
+/// types, spelled `_Atomic(long) *`, are not atomic themselves.
+fn is_atomic(type_: &Type) -> bool {
+    let canonical = type_.get_canonical_type().get_display_name();
+    let inner = match canonical
+        .strip_prefix("_Atomic(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        Some(inner) => inner,
+        None => return false,
+    };
+    // Make sure the parentheses that were stripped belong together, unlike in
+    // e.g. `_Atomic(int) (*)(void)`.
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return false,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    true
+}
+
+fn is_unsigned(type_: &Type) -> bool {
+    use clang::TypeKind::*;
+
+    matches!(
+        type_.get_canonical_type().get_kind(),
+        Bool | CharU | UChar | UShort | UInt | ULong | ULongLong | UInt128
+    )
+}
+
+/// Returns whether the initializer of an enum constant evaluates to something
+/// other than the value clang stored for it, which happens when the value was
+/// truncated to fit the underlying type of the enum.
+fn is_out_of_range(constant: &Entity, value: i64, unsigned: u64) -> bool {
+    let mut initializer = match constant.get_children().into_iter().next() {
+        Some(initializer) => initializer,
+        None => return false,
+    };
+    // The conversion to the underlying type is an implicit cast, which is
+    // exposed as an unexposed expression wrapping the written initializer.
+    while initializer.get_kind() == EntityKind::UnexposedExpr {
+        match initializer.get_children().into_iter().next() {
+            Some(child) => initializer = child,
+            None => break,
+        }
+    }
+
+    match initializer.evaluate() {
+        Some(EvaluationResult::SignedInteger(initial)) => initial != value,
+        Some(EvaluationResult::UnsignedInteger(initial)) => initial != unsigned,
+        _ => false,
+    }
+}
+
+fn parse_union<'tu>(entity: &Entity<'tu>, name: String, context: &mut Context<'tu>) {
+    parse_nested(entity, &name, context);
+    parse_constants(entity, &name, context);
+
+    let mut fields: Vec<UnionField> = get_fields(entity)
+        .iter()
+        .map(|field| {
+            let type_ = field.get_type().unwrap();
+            let info = get_declared_type_info(field, &type_, context);
+            UnionField {
+                name: declared_name(field),
+                type_: field_type_name(&type_, context),
+                info,
+                requested_alignment: requested_alignment(field),
+                is_largest: false,
+            }
+        })
+        .collect();
+
+    // The first of the largest members determines the size of the union,
+    // anything beyond it is alignment padding.
+    let max = fields.iter().filter_map(|field| field.info.size).max();
+    if let Some(field) = fields.iter_mut().find(|field| field.info.size == max) {
+        field.is_largest = max.is_some();
+    }
+
+    let (size, alignment) = get_layout(entity);
+    context.push(
+        entity,
+        Types::Union(UnionType {
+            name,
+            location: None,
+            is_definition: entity.is_definition(),
+            size,
+            alignment,
+            requested_alignment: requested_alignment(entity),
+            is_packed: is_packed(entity, alignment),
+            fields,
+        }),
+    );
+}
+
+/// Returns the sum of the sizes in bytes of the fields of the struct declared
+/// by `entity`, rounding up the total of any bit-fields.
+fn fields_size(entity: &Entity) -> usize {
+    let bits: usize = get_fields(entity)
+        .iter()
+        .map(|field| match field.get_bit_field_width() {
+            Some(width) => width,
+            // Flexible array members have no size.
+            None => field
+                .get_type()
+                .and_then(|type_| type_.get_sizeof().ok())
+                .map_or(0, |size| size * 8),
+        })
+        .sum();
+    bits.div_ceil(8)
+}
+
+/// Returns whether the members of the record declared by `entity` are packed,
+/// which is the case if any of them is less aligned than its type. This is
+/// decided using the layout computed by clang, which accounts for the
+/// `#pragma pack` in effect where the record is declared.
+fn is_packed(entity: &Entity, alignment: Option<usize>) -> bool {
+    let alignment = match alignment {
+        Some(alignment) => alignment,
+        None => return false,
+    };
+    // Bit-fields may start anywhere within their storage.
+    get_fields(entity)
+        .iter()
+        .filter(|field| !field.is_bit_field())
+        .any(|field| {
+            let natural = match field.get_type().map(|type_| type_.get_alignof()) {
+                Some(Ok(natural)) => natural,
+                _ => return false,
+            };
+            let offset = field.get_offset_of_field();
+            natural > alignment || matches!(offset, Ok(bits) if bits / 8 % natural != 0)
+        })
+}
+
+/// Returns the size and alignment in bytes of the type declared by `entity`.
+/// These are unavailable for incomplete types such as forward declarations.
+fn get_layout(entity: &Entity) -> (Option<usize>, Option<usize>) {
+    match entity.get_type() {
+        Some(type_) => (type_.get_sizeof().ok(), type_.get_alignof().ok()),
+        None => (None, None),
+    }
+}
+
+/// Returns the largest alignment requested for `entity` by an alignment
+/// specifier or attribute. Only alignments given as a plain integer can be
+/// recovered, as libclang does not expose the value of the attribute.
+fn requested_alignment(entity: &Entity) -> Option<usize> {
+    entity
+        .get_children()
+        .iter()
+        .filter(|child| child.get_kind() == EntityKind::AlignedAttr)
+        .filter_map(|attribute| {
+            let tokens = attribute.get_range()?.tokenize();
+            let mut values = tokens.iter().filter(|token| {
+                !matches!(
+                    token.get_kind(),
+                    TokenKind::Punctuation | TokenKind::Keyword | TokenKind::Comment
+                ) && !matches!(
+                    token.get_spelling().as_str(),
+                    "alignas" | "aligned" | "__aligned__" | "__attribute__"
+                )
+            });
+            match (values.next(), values.next()) {
+                (Some(value), None) if value.get_kind() == TokenKind::Literal => {
+                    parse_integer(&value.get_spelling())
+                }
+                _ => None,
+            }
+        })
+        .max()
+}
+
+/// Parses a C integer literal such as `16`, `0x10` or `16u`.
+fn parse_integer(literal: &str) -> Option<usize> {
+    let literal = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        usize::from_str_radix(hex, 16).ok()
+    } else if literal.len() > 1 && literal.starts_with('0') {
+        usize::from_str_radix(&literal[1..], 8).ok()
+    } else {
+        literal.parse().ok()
+    }
+}
+
+/// Returns the spelling of `type_`. Types spelled using GNU extensions such as
+/// `typeof(x)` or `__auto_type` are meaningless outside of the header, so the
+/// canonical type is spelled instead.
+fn display_name(type_: &Type) -> String {
+    let name = type_.get_display_name();
+    if name.contains("typeof") || name.contains("__auto_type") {
+        type_.get_canonical_type().get_display_name()
+    } else {
+        name
+    }
+}
+
+fn get_type_info(type_: &Type) -> TypeInfo {
+    let canonical = type_.get_canonical_type();
+
+    // Pointers are followed through typedefs, keeping the spelling of the
+    // pointee where possible so that function pointer signatures refer to the
+    // types as written.
+    // The pointers of an array are those of its elements, e.g. for a table
+    // of function pointers like `int (*[4])(int)`.
+    let mut element = *type_;
+    while is_array(&element) {
+        match element
+            .get_element_type()
+            .or_else(|| element.get_canonical_type().get_element_type())
+        {
+            Some(next) => element = next,
+            None => break,
+        }
+    }
+
+    let mut pointer_depth = 0;
+    let mut pointee = element;
+    while let Some(next) = pointee
+        .get_pointee_type()
+        .or_else(|| pointee.get_canonical_type().get_pointee_type())
+    {
+        pointer_depth += 1;
+        pointee = next;
+    }
+    let pointee_canonical = pointee.get_canonical_type();
+    let (array_sizes, array_element) = array_dimensions(type_);
+
+    let function = match pointee_canonical.get_kind() {
+        clang::TypeKind::FunctionPrototype | clang::TypeKind::FunctionNoPrototype => {
+            Some(Box::new(get_signature(&pointee)))
+        }
+        _ => None,
+    };
+
+    TypeInfo {
+        type_kind: get_type_kind(&canonical),
+        canonical: canonical.get_display_name(),
+        size: type_.get_sizeof().ok(),
+        alignment: type_.get_alignof().ok(),
+        pointer_depth,
+        is_const: pointee_canonical.is_const_qualified(),
+        function,
+        array_sizes,
+        array_element,
+        array_size_spellings: None,
+    }
+}
+
+fn is_array(type_: &Type) -> bool {
+    get_type_kind(&type_.get_canonical_type()) == TypeKind::Array
+}
+
+/// Returns the length of every dimension of the array type `type_` and the
+/// spelling of its element type, e.g. `[2, 3]` and `vec3` for `vec3 [2][3]`.
+fn array_dimensions(type_: &Type) -> (Vec<usize>, Option<String>) {
+    let mut sizes = Vec::new();
+    let mut type_ = *type_;
+    loop {
+        let canonical = type_.get_canonical_type();
+        if canonical.get_kind() != clang::TypeKind::ConstantArray {
+            break;
+        }
+        // The element type is taken from the array as spelled where possible,
+        // and otherwise from the canonical type, e.g. for a typedef.
+        let element = type_
+            .get_element_type()
+            .or_else(|| canonical.get_element_type());
+        match (canonical.get_size(), element) {
+            (Some(size), Some(element)) => {
+                sizes.push(size);
+                type_ = element;
+            }
+            _ => break,
+        }
+    }
+
+    if sizes.is_empty() {
+        (sizes, None)
+    } else {
+        (sizes, Some(display_name(&type_)))
+    }
+}
+
+/// Returns the type information of a field or variable declared by
+/// `entity`, including what can only be learned from the declaration.
+fn get_declared_type_info(entity: &Entity, type_: &Type, context: &Context) -> TypeInfo {
+    let mut info = get_type_info(type_);
+    name_parameters(entity, &mut info);
+    if context.array_size_spellings && !info.array_sizes.is_empty() {
+        let spellings = array_size_spellings(entity);
+        if spellings.len() == info.array_sizes.len() {
+            info.array_size_spellings = Some(spellings);
+        }
+    }
+    info
+}
+
+/// Returns the spelling of every array length in the declaration `entity`,
+/// e.g. `["ROWS", "COLUMNS + 1"]` for `int grid[ROWS][COLUMNS + 1]`.
+fn array_size_spellings(entity: &Entity) -> Vec<String> {
+    let tokens = match entity.get_range() {
+        Some(range) => range.tokenize(),
+        None => return Vec::new(),
+    };
+
+    let mut spellings = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    for token in &tokens {
+        let spelling = token.get_spelling();
+        match spelling.as_str() {
+            "[" => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            "]" => {
+                depth -= 1;
+                if depth == 0 {
+                    spellings.push(current.join(" "));
+                    current.clear();
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        if depth > 0 {
+            current.push(spelling);
+        }
+    }
+    spellings
+}
+
+/// Returns the signature of the function type `type_`. Parameter names are
+/// not part of the type, so they are left out.
+fn get_signature(type_: &Type) -> FunctionSignature {
+    let return_type = type_.get_result_type().unwrap();
+    let parameters = type_
+        .get_argument_types()
+        .unwrap_or_default()
+        .iter()
+        .map(|parameter| Parameter {
+            name: None,
+            type_: display_name(parameter),
+            info: get_type_info(parameter),
+        })
+        .collect();
+
+    FunctionSignature {
+        return_type: display_name(&return_type),
+        return_info: get_type_info(&return_type),
+        parameters,
+        is_variadic: type_.is_variadic(),
+        has_unspecified_parameters: has_unspecified_parameters(type_),
+    }
+}
+
+/// Returns whether the function type `type_` was declared without a
+/// prototype, e.g. `int f()` in C, as opposed to `int f(void)`.
+fn has_unspecified_parameters(type_: &Type) -> bool {
+    type_.get_canonical_type().get_kind() == clang::TypeKind::FunctionNoPrototype
+}
+
+/// Fills in the parameter names of a function pointer declared by `entity`,
+/// e.g. `a` and `b` for a field `int (*cmp)(const void *a, const void *b)`.
+fn name_parameters(entity: &Entity, info: &mut TypeInfo) {
+    let signature = match &mut info.function {
+        Some(signature) => signature,
+        None => return,
+    };
+    let names: Vec<Option<String>> = entity
+        .get_children()
+        .iter()
+        .filter(|child| child.get_kind() == EntityKind::ParmDecl)
+        .map(|parameter| parameter.get_name())
+        .collect();
+    // Parameters of a function pointer declared through a typedef belong to
+    // the typedef rather than the field.
+    if names.len() == signature.parameters.len() {
+        for (parameter, name) in signature.parameters.iter_mut().zip(names) {
+            parameter.name = name;
+        }
+    }
+}
+
+fn get_type_kind(canonical: &Type) -> TypeKind {
+    use clang::TypeKind::*;
+
+    match canonical.get_kind() {
+        Void | Bool | CharS | CharU | SChar | UChar | WChar | Char16 | Char32 | Short | UShort
+        | Int | UInt | Long | ULong | LongLong | ULongLong | Int128 | UInt128 | Half | Float16
+        | Float | Double | LongDouble | Float128 => TypeKind::Primitive,
+        Pointer | BlockPointer | MemberPointer | LValueReference | RValueReference
+        | ObjCObjectPointer => TypeKind::Pointer,
+        ConstantArray | IncompleteArray | VariableArray | DependentSizedArray => TypeKind::Array,
+        Record => TypeKind::Record,
+        Enum => TypeKind::Enum,
+        FunctionPrototype | FunctionNoPrototype => TypeKind::Function,
+        _ => TypeKind::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_literals() {
+        assert_eq!(parse_integer("16"), Some(16));
+        assert_eq!(parse_integer("0x10"), Some(16));
+        assert_eq!(parse_integer("0X1f"), Some(31));
+        assert_eq!(parse_integer("16u"), Some(16));
+        assert_eq!(parse_integer("16UL"), Some(16));
+        assert_eq!(parse_integer("010"), Some(8));
+        assert_eq!(parse_integer("0"), Some(0));
+        assert_eq!(parse_integer("N"), None);
+        assert_eq!(parse_integer("09"), None);
+    }
+}
@@ -0,0 +1,34 @@
+//! Builders of the types the unit tests work on.
+
+use crate::Types;
+use serde_json::{json, Value};
+
+/// Deserializes types given as JSON, in the format they are emitted in.
+pub(crate) fn types(json: Value) -> Vec<Types> {
+    serde_json::from_value(json).unwrap()
+}
+
+pub(crate) fn names(types: &[Types]) -> Vec<&str> {
+    types.iter().map(Types::name).collect()
+}
+
+/// A field of type `type_`, to which tests add whatever else they need.
+pub(crate) fn field(name: Option<&str>, type_: &str, offset: Option<usize>) -> Value {
+    json!({"name": name, "type_": type_, "offset": offset, "requested_alignment": null,
+        "bit_width": null, "bit_offset": null})
+}
+
+/// A struct of `size` bytes with the given fields, or a declaration of one
+/// if `size` is None.
+pub(crate) fn struct_(name: &str, size: Option<usize>, fields: Vec<Value>) -> Value {
+    json!({"Struct": {
+        "name": name, "location": null, "is_definition": size.is_some(), "size": size,
+        "alignment": null, "fields_size": null, "requested_alignment": null,
+        "fields": fields,
+    }})
+}
+
+/// A variable of type `type_`.
+pub(crate) fn variable(name: &str, type_: &str) -> Value {
+    json!({"Variable": {"name": name, "location": null, "type_": type_}})
+}
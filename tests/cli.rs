@@ -0,0 +1,40 @@
+//! Runs the ctypeparser binary on the headers in `tests/fixtures`. Like the
+//! fixture tests, these fail if libclang can't be loaded, unless
+//! `CTYPEPARSER_SKIP_FIXTURES` is set.
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+/// Runs ctypeparser with `args` and the environment variables in `envs`,
+/// returning its output or None if libclang is unavailable and the test is
+/// skipped.
+fn run(args: &[&str], envs: &[(&str, &str)]) -> Option<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_ctypeparser"))
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
+        .args(args)
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("failed to initialize libclang") {
+        common::skip_or_fail(&stderr);
+        return None;
+    }
+    assert!(output.status.success(), "ctypeparser failed: {}", stderr);
+    Some(String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn clang_args_from_environment_and_command_line() {
+    let output = run(
+        &["--names", "clang_args.h", "--", "-DFROM_CLI", "-DVALUE=2"],
+        &[("CTYPEPARSER_CLANG_ARGS", "-DFROM_ENV -DVALUE=1")],
+    );
+    // Both sources are used, with the command line taking precedence.
+    if let Some(output) = output {
+        assert_eq!(output, "both\ncli_wins\n");
+    }
+}
@@ -0,0 +1,17 @@
+//! Helpers shared by the integration tests.
+
+/// Set to skip the tests that need libclang when it can't be loaded, rather
+/// than failing them.
+const SKIP_VAR: &str = "CTYPEPARSER_SKIP_FIXTURES";
+
+/// Reports that libclang failed to load with `error`, which fails the test
+/// unless skipping was asked for.
+pub fn skip_or_fail(error: &str) {
+    if std::env::var_os(SKIP_VAR).is_none() {
+        panic!(
+            "libclang can't be loaded, set {} to skip the tests that need it: {}",
+            SKIP_VAR, error
+        );
+    }
+    eprintln!("skipping: {}", error);
+}
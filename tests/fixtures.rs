@@ -0,0 +1,535 @@
+//! Parses the headers in `tests/fixtures`. These need libclang and fail if it
+//! can't be loaded, unless `CTYPEPARSER_SKIP_FIXTURES` is set.
+#![cfg(feature = "clang")]
+
+mod common;
+
+use ctypeparser::emit::{emit, Format};
+use ctypeparser::{
+    EnumType, ParseError, ParseOptions, StructType, TypeDefType, TypeKind, Types, UnionType,
+    VariableType, Warning,
+};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Only one instance of `Clang` may exist at a time, so fixtures are parsed
+/// one after another.
+static CLANG: Mutex<()> = Mutex::new(());
+
+/// Parses the fixture `name`, returning None if libclang is unavailable and
+/// the test is skipped.
+fn parse_with(options: ParseOptions, name: &str) -> Option<(Vec<Types>, Vec<Warning>)> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    let _guard = CLANG
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match options.parse_with_warnings(&path) {
+        Err(ParseError::ClangInit(message)) => {
+            common::skip_or_fail(&message);
+            None
+        }
+        result => Some(result.unwrap()),
+    }
+}
+
+fn parse(name: &str) -> Option<(Vec<Types>, Vec<Warning>)> {
+    parse_with(ParseOptions::new(), name)
+}
+
+fn find<'a>(types: &'a [Types], kind: &str, name: &str) -> Option<&'a Types> {
+    types
+        .iter()
+        .find(|type_| type_.kind() == kind && type_.name() == name)
+}
+
+fn typedef<'a>(types: &'a [Types], name: &str) -> &'a TypeDefType {
+    match find(types, "typedef", name) {
+        Some(Types::TypeDef(typedef)) => typedef,
+        _ => panic!("no typedef `{}` in {:#?}", name, types),
+    }
+}
+
+fn struct_<'a>(types: &'a [Types], name: &str) -> &'a StructType {
+    match find(types, "struct", name) {
+        Some(Types::Struct(struct_)) => struct_,
+        _ => panic!("no struct `{}` in {:#?}", name, types),
+    }
+}
+
+fn enum_<'a>(types: &'a [Types], name: &str) -> &'a EnumType {
+    match find(types, "enum", name) {
+        Some(Types::Enum(enum_)) => enum_,
+        _ => panic!("no enum `{}` in {:#?}", name, types),
+    }
+}
+
+fn union_<'a>(types: &'a [Types], name: &str) -> &'a UnionType {
+    match find(types, "union", name) {
+        Some(Types::Union(union)) => union,
+        _ => panic!("no union `{}` in {:#?}", name, types),
+    }
+}
+
+fn variable<'a>(types: &'a [Types], name: &str) -> &'a VariableType {
+    match find(types, "variable", name) {
+        Some(Types::Variable(variable)) => variable,
+        _ => panic!("no variable `{}` in {:#?}", name, types),
+    }
+}
+
+#[test]
+fn typedefs_without_underlying_type() {
+    let (types, warnings) = match parse("no_underlying_type.hpp") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    assert_eq!(typedef(&types, "ok_t").underlying, "int");
+    typedef(&types, "holder_t");
+    // Typedefs without an underlying type are left out rather than panicking.
+    assert!(find(&types, "typedef", "broken_t").is_none());
+    assert!(warnings.contains(&Warning::NoUnderlyingType {
+        typedef: "broken_t".to_string()
+    }));
+}
+
+#[test]
+fn multiple_declarators() {
+    let (types, _) = match parse("multiple_declarators.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    assert_eq!(typedef(&types, "a_t").underlying, "int");
+    assert_eq!(typedef(&types, "b_t").underlying, "int");
+    // The anonymous struct is named after the first declarator naming it.
+    assert_eq!(struct_(&types, "s_t").fields.len(), 1);
+    assert_eq!(typedef(&types, "s_ptr_t").info.pointer_depth, 1);
+    // Variables share the type named after the first of them.
+    assert_eq!(struct_(&types, "::p").fields.len(), 1);
+    assert_eq!(variable(&types, "p").type_, "::p");
+    assert_eq!(variable(&types, "q").type_, "::p");
+}
+
+#[test]
+fn enums_nested_in_structs() {
+    let (types, _) = match parse("nested_enum.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let kind = enum_(&types, "shape::kind");
+    let constants: Vec<(&str, i64)> = kind
+        .fields
+        .iter()
+        .map(|field| (field.name.as_str(), field.value))
+        .collect();
+    assert_eq!(constants, [("CIRCLE", 0), ("SQUARE", 4)]);
+    assert_eq!(enum_(&types, "unit").fields.len(), 2);
+    find(&types, "union", "shape::size").unwrap();
+
+    let shape = struct_(&types, "shape");
+    let fields: Vec<&str> = shape
+        .fields
+        .iter()
+        .map(|field| field.type_.as_str())
+        .collect();
+    assert_eq!(fields[0], "shape::kind");
+    assert_eq!(fields[2], "shape::size");
+}
+
+#[test]
+fn anonymous_members() {
+    let (types, _) = match parse("anonymous_members.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    // The anonymous union is named after its index among the children of
+    // `value`, lacking a field to be named after.
+    let value = struct_(&types, "value");
+    assert_eq!(value.size, Some(16));
+    assert_eq!(value.fields.len(), 2);
+    assert_eq!(value.fields[1].name, None);
+    assert_eq!(value.fields[1].type_, "value::1");
+    assert_eq!(value.fields[1].offset, Some(8));
+    assert_eq!(value.fields_size, Some(12));
+
+    let members = &union_(&types, "value::1").fields;
+    assert_eq!(members.len(), 2);
+    let fields: Vec<Vec<&str>> = members
+        .iter()
+        .map(|member| {
+            assert_eq!(member.name, None);
+            struct_(&types, &member.type_)
+                .fields
+                .iter()
+                .map(|field| field.name.as_deref().unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(fields, [vec!["lo", "hi"], vec!["wide"]]);
+}
+
+#[test]
+fn class_constants() {
+    let (types, _) = match parse("class_constants.hpp") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let constant = |name: &str| match find(&types, "constant", name) {
+        Some(Types::Constant(constant)) => constant,
+        _ => panic!("no constant `{}` in {:#?}", name, types),
+    };
+    assert_eq!(constant("Foo::FOO").value, 5);
+    assert_eq!(constant("Foo::FOO").type_, "const int");
+    assert_eq!(constant("Foo::BAR").value, 16);
+    // Static members are not part of the layout of the class.
+    let foo = struct_(&types, "Foo");
+    assert_eq!(foo.size, Some(4));
+    let fields: Vec<&str> = foo
+        .fields
+        .iter()
+        .map(|field| field.name.as_deref().unwrap())
+        .collect();
+    assert_eq!(fields, ["value"]);
+}
+
+#[test]
+fn forward_declared_enums() {
+    let (types, _) = match parse("forward_enum.hpp") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let color = enum_(&types, "color");
+    assert!(!color.is_definition);
+    assert_eq!(color.underlying.as_deref(), Some("unsigned char"));
+    assert!(color.fields.is_empty());
+
+    // The forward declaration is replaced by the definition.
+    let later = enum_(&types, "later");
+    assert!(later.is_definition);
+    assert_eq!(later.underlying.as_deref(), Some("short"));
+    assert_eq!(later.fields.len(), 2);
+    assert_eq!(
+        types.iter().filter(|type_| type_.name() == "later").count(),
+        1
+    );
+}
+
+#[test]
+fn integer_widths() {
+    let (types, _) = match parse("integers.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let integers = struct_(&types, "integers");
+    let field = |name: &str| {
+        let mut fields = integers.fields.iter();
+        fields
+            .find(|field| field.name.as_deref() == Some(name))
+            .unwrap()
+    };
+    for (name, canonical, size) in [
+        ("c", "char", 1),
+        ("sc", "signed char", 1),
+        ("uc", "unsigned char", 1),
+        ("s", "short", 2),
+        ("us", "unsigned short", 2),
+        ("i", "int", 4),
+        ("ui", "unsigned int", 4),
+        ("ll", "long long", 8),
+        ("ull", "unsigned long long", 8),
+        ("lli", "long long", 8),
+        ("ulli", "unsigned long long", 8),
+    ] {
+        assert_eq!(field(name).info.canonical, canonical);
+        assert_eq!(field(name).info.size, Some(size));
+    }
+    assert_eq!(field("l").info.canonical, "long");
+    assert_eq!(field("ul").info.canonical, "unsigned long");
+
+    let rust = emit(Format::Rust, &types).unwrap();
+    for (name, rust_type) in [
+        ("c", "c_char"),
+        ("sc", "c_schar"),
+        ("uc", "c_uchar"),
+        ("s", "c_short"),
+        ("us", "c_ushort"),
+        ("i", "c_int"),
+        ("ui", "c_uint"),
+        ("l", "c_long"),
+        ("ul", "c_ulong"),
+        ("ll", "c_longlong"),
+        ("ull", "c_ulonglong"),
+        ("lli", "c_longlong"),
+        ("ulli", "c_ulonglong"),
+    ] {
+        let line = format!("    pub {}: {},\n", name, rust_type);
+        assert!(rust.contains(&line), "no `{}` in {}", line.trim(), rust);
+    }
+}
+
+#[test]
+fn int128_fields() {
+    let (types, _) = match parse("int128.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let wide = struct_(&types, "wide");
+    let fields: Vec<(&str, &str, Option<usize>)> = wide
+        .fields
+        .iter()
+        .map(|field| {
+            (
+                field.type_.as_str(),
+                field.info.canonical.as_str(),
+                field.info.size,
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("__int128", "__int128", Some(16)),
+            ("unsigned __int128", "unsigned __int128", Some(16)),
+            ("__int128_t", "__int128", Some(16)),
+            ("__uint128_t", "unsigned __int128", Some(16)),
+        ]
+    );
+
+    let rust = emit(Format::Rust, &types).unwrap();
+    for (name, rust_type) in [("s", "i128"), ("u", "u128"), ("st", "i128"), ("ut", "u128")] {
+        let line = format!("    pub {}: {},\n", name, rust_type);
+        assert!(rust.contains(&line), "no `{}` in {}", line.trim(), rust);
+    }
+}
+
+#[test]
+fn atomic_fields() {
+    let (types, _) = match parse("atomic.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let stats = struct_(&types, "stats");
+    let fields: Vec<(&str, bool)> = stats
+        .fields
+        .iter()
+        .map(|field| (field.name.as_deref().unwrap(), field.is_atomic))
+        .collect();
+    // Pointers to atomic types are not atomic themselves.
+    assert_eq!(
+        fields,
+        [("counter", true), ("last", false), ("total", false)]
+    );
+    assert_eq!(stats.fields[0].info.canonical, "_Atomic(long)");
+
+    let rust = emit(Format::Rust, &types).unwrap();
+    assert!(rust.contains("    pub counter: c_long,\n"));
+}
+
+#[test]
+fn unsigned_enum_values_beyond_i64() {
+    let (types, warnings) = match parse("unsigned_enum.hpp") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let values: Vec<i64> = enum_(&types, "flags")
+        .fields
+        .iter()
+        .map(|field| field.value)
+        .collect();
+    assert_eq!(values, [1, i64::MIN, -1]);
+    assert_eq!(
+        warnings,
+        [
+            Warning::EnumValueOutOfRange {
+                constant: "flags::HIGH".to_string(),
+                value: 0x8000_0000_0000_0000,
+                stored: i64::MIN,
+            },
+            Warning::EnumValueOutOfRange {
+                constant: "flags::MAX".to_string(),
+                value: u64::MAX,
+                stored: -1,
+            },
+        ]
+    );
+
+    let rust = emit(Format::Rust, &types).unwrap();
+    assert!(rust.contains("pub const MAX: flags = -1i64 as flags;\n"));
+}
+
+#[test]
+fn self_including_headers() {
+    let (types, _) = match parse("self_including.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let names: Vec<&str> = types.iter().map(Types::name).collect();
+    assert_eq!(names, ["first", "counter", "second", "third"]);
+}
+
+#[test]
+fn pragma_pack_scopes() {
+    let (types, _) = match parse("pragma_pack.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    for (name, is_packed, alignment) in [
+        ("natural", false, 4),
+        ("packed", true, 1),
+        ("outer", true, 1),
+        ("outer::inner", true, 1),
+        ("packed_2", true, 2),
+        ("after", false, 4),
+        ("chars", false, 1),
+    ] {
+        let struct_ = struct_(&types, name);
+        assert_eq!(struct_.is_packed, is_packed, "{}", name);
+        assert_eq!(struct_.alignment, Some(alignment), "{}", name);
+    }
+    match find(&types, "union", "packed_union") {
+        Some(Types::Union(union)) => assert!(union.is_packed),
+        _ => panic!("no union `packed_union` in {:#?}", types),
+    }
+
+    let rust = emit(Format::Rust, &types).unwrap();
+    assert!(rust.contains("#[repr(C, packed(2))]\n#[derive(Copy, Clone)]\npub struct packed_2 {"));
+    assert!(rust.contains("#[repr(C)]\n#[derive(Copy, Clone)]\npub struct after {"));
+}
+
+#[test]
+fn requested_alignments() {
+    let (types, _) = match parse("alignment.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let aligned_field = struct_(&types, "aligned_field");
+    assert_eq!(aligned_field.requested_alignment, None);
+    assert_eq!(aligned_field.alignment, Some(16));
+    assert_eq!(aligned_field.size, Some(32));
+    assert_eq!(aligned_field.fields[0].requested_alignment, None);
+    assert_eq!(aligned_field.fields[1].requested_alignment, Some(16));
+    assert_eq!(aligned_field.fields[1].offset, Some(16));
+
+    let aligned_attribute = struct_(&types, "aligned_attribute");
+    assert_eq!(aligned_attribute.requested_alignment, Some(8));
+    assert_eq!(aligned_attribute.alignment, Some(8));
+    assert!(!aligned_attribute.is_packed);
+    let rust = emit(Format::Rust, &types).unwrap();
+    assert!(rust
+        .contains("#[repr(C, align(8))]\n#[derive(Copy, Clone)]\npub struct aligned_attribute {"));
+
+    let (types, _) = match parse("alignas.hpp") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let aligned_struct = struct_(&types, "aligned_struct");
+    assert_eq!(aligned_struct.requested_alignment, Some(32));
+    assert_eq!(aligned_struct.alignment, Some(32));
+    assert_eq!(aligned_struct.size, Some(32));
+}
+
+#[test]
+fn array_bounds_from_enums_and_macros() {
+    let options = ParseOptions::new().array_size_spellings(true);
+    let (types, _) = match parse_with(options, "array_bounds.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let fields = &struct_(&types, "palette").fields;
+    let sizes: Vec<&[usize]> = fields.iter().map(|f| &f.info.array_sizes[..]).collect();
+    assert_eq!(sizes, [&[3][..], &[17], &[3, 2]]);
+    let spellings: Vec<Vec<String>> = fields
+        .iter()
+        .map(|field| field.info.array_size_spellings.clone().unwrap())
+        .collect();
+    assert_eq!(
+        spellings,
+        [
+            vec!["COLOR_COUNT"],
+            vec!["MAX_LEN + 1"],
+            vec!["COLOR_COUNT", "2"],
+        ]
+    );
+}
+
+#[test]
+fn typedef_chains() {
+    let (types, _) = match parse("typedef_chain.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    assert_eq!(typedef(&types, "A").resolved_chain, ["int"]);
+    assert_eq!(typedef(&types, "B").resolved_chain, ["A", "int"]);
+    let c = typedef(&types, "C");
+    assert_eq!(c.underlying, "B");
+    assert_eq!(c.resolved_chain, ["B", "A", "int"]);
+    assert_eq!(c.info.canonical, "int");
+}
+
+#[test]
+fn typedef_kinds() {
+    let (types, _) = match parse("typedef_kinds.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    for (name, type_kind) in [
+        ("point_t", TypeKind::Record),
+        ("color_t", TypeKind::Enum),
+        ("point_ptr", TypeKind::Pointer),
+        ("handle_t", TypeKind::Pointer),
+        ("callback_fn", TypeKind::Function),
+    ] {
+        assert_eq!(typedef(&types, name).info.type_kind, type_kind, "{}", name);
+    }
+    let handle_t = typedef(&types, "handle_t");
+    assert_eq!(handle_t.resolved_chain, ["point_ptr", "point_t *"]);
+    assert_eq!(handle_t.info.pointer_depth, 1);
+}
+
+#[test]
+fn array_typedefs() {
+    let (types, _) = match parse("array_typedefs.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let vec3 = &typedef(&types, "vec3").info;
+    assert_eq!(vec3.type_kind, TypeKind::Array);
+    assert_eq!(vec3.array_sizes, [3]);
+    assert_eq!(vec3.array_element.as_deref(), Some("int"));
+    assert_eq!(vec3.size, Some(12));
+
+    let mat4 = &typedef(&types, "mat4").info;
+    assert_eq!(mat4.type_kind, TypeKind::Array);
+    assert_eq!(mat4.array_sizes, [4, 4]);
+    assert_eq!(mat4.array_element.as_deref(), Some("float"));
+    assert_eq!(mat4.size, Some(64));
+
+    let rust = emit(Format::Rust, &types).unwrap();
+    assert!(rust.contains("pub type vec3 = [c_int; 3];\n"));
+    assert!(rust.contains("pub type mat4 = [[c_float; 4]; 4];\n"));
+}
+
+#[test]
+fn void_and_unspecified_parameter_lists() {
+    // C23 gives `f()` the meaning of `f(void)`.
+    let options = ParseOptions::new().clang_args(&["-std=c11"]);
+    let (types, _) = match parse_with(options, "parameter_lists.h") {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let signature = |name: &str| match find(&types, "function", name) {
+        Some(Types::Function(function)) => &function.signature,
+        _ => panic!("no function `{}` in {:#?}", name, types),
+    };
+    assert!(signature("explicit_void").parameters.is_empty());
+    assert!(!signature("explicit_void").has_unspecified_parameters);
+    assert!(signature("unspecified").parameters.is_empty());
+    assert!(signature("unspecified").has_unspecified_parameters);
+    assert!(signature("variadic").is_variadic);
+    assert!(!signature("variadic").has_unspecified_parameters);
+
+    // Function pointers keep the distinction as well.
+    let callback = variable(&types, "callback").info.function.as_ref().unwrap();
+    assert!(callback.has_unspecified_parameters);
+}
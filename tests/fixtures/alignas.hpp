@@ -0,0 +1,4 @@
+// An alignment requested with a C++11 specifier.
+struct alignas(32) aligned_struct {
+    int x;
+};
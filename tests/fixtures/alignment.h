@@ -0,0 +1,9 @@
+// Alignments requested with C11 specifiers and GNU attributes.
+struct aligned_field {
+    char c;
+    _Alignas(16) int x;
+};
+
+struct aligned_attribute {
+    int x;
+} __attribute__((aligned(8)));
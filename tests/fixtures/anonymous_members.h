@@ -0,0 +1,13 @@
+// Anonymous members, whose fields are accessed as if they were the parent's.
+struct value {
+    int kind;
+    union {
+        struct {
+            int lo;
+            int hi;
+        };
+        struct {
+            long long wide;
+        };
+    };
+};
@@ -0,0 +1,8 @@
+enum color { RED, GREEN, BLUE, COLOR_COUNT };
+#define MAX_LEN 16
+
+struct palette {
+    int colors[COLOR_COUNT];
+    char name[MAX_LEN + 1];
+    int grid[COLOR_COUNT][2];
+};
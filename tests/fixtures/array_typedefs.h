@@ -0,0 +1,2 @@
+typedef int vec3[3];
+typedef float mat4[4][4];
@@ -0,0 +1,6 @@
+// Atomic fields, which libclang only exposes through their canonical spelling.
+struct stats {
+    _Atomic long counter;
+    _Atomic(int) *last;
+    long total;
+};
@@ -0,0 +1,7 @@
+#if defined(FROM_ENV) && defined(FROM_CLI)
+struct both { int x; };
+#endif
+
+#if VALUE == 2
+struct cli_wins { int x; };
+#endif
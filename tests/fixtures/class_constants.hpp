@@ -0,0 +1,9 @@
+// Constants declared as static members of a class.
+class Foo {
+public:
+    static const int FOO = 5;
+    static const unsigned BAR = 1u << 4;
+
+private:
+    int value;
+};
@@ -0,0 +1,4 @@
+enum class color : unsigned char;
+
+enum class later : short;
+enum class later : short { first, second };
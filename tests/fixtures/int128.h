@@ -0,0 +1,7 @@
+// 128-bit integers, spelled as keywords and as their builtin typedefs.
+struct wide {
+    __int128 s;
+    unsigned __int128 u;
+    __int128_t st;
+    __uint128_t ut;
+};
@@ -0,0 +1,15 @@
+struct integers {
+    char c;
+    signed char sc;
+    unsigned char uc;
+    short s;
+    unsigned short us;
+    int i;
+    unsigned int ui;
+    long l;
+    unsigned long ul;
+    long long ll;
+    unsigned long long ull;
+    long long int lli;
+    unsigned long long int ulli;
+};
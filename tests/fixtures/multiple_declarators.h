@@ -0,0 +1,3 @@
+typedef int a_t, b_t;
+typedef struct { int x; } s_t, *s_ptr_t;
+struct { int x; } p, q;
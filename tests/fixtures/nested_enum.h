@@ -0,0 +1,8 @@
+struct shape {
+    enum { CIRCLE, SQUARE = 4 } kind;
+    enum unit { MM, INCH } unit;
+    union {
+        int radius;
+        int side;
+    } size;
+};
@@ -0,0 +1,10 @@
+// Typedefs libclang may fail to make sense of, along with ones it can't.
+template <typename T> struct holder {
+    typedef typename T::type type;
+};
+
+typedef undeclared_t broken_t;
+typedef decltype(undeclared) deduced_t;
+
+typedef int ok_t;
+typedef holder<int> holder_t;
@@ -0,0 +1,4 @@
+int explicit_void(void);
+int unspecified();
+int variadic(const char *format, ...);
+int (*callback)();
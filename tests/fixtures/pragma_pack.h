@@ -0,0 +1,17 @@
+struct natural { char c; int i; };
+
+#pragma pack(push, 1)
+struct packed { char c; int i; };
+struct outer {
+    char c;
+    int i;
+    struct { char d; int i; } inner;
+};
+#pragma pack(push, 2)
+struct packed_2 { char c; int i; };
+#pragma pack(pop)
+union packed_union { char c[5]; int i; };
+#pragma pack(pop)
+
+struct after { char c; int i; };
+struct chars { char a; char b; };
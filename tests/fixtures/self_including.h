@@ -0,0 +1,23 @@
+// An amalgamated header that includes itself to emit its second section,
+// declaring some things in both.
+#ifndef SECOND_SECTION
+#ifndef SELF_INCLUDING_H
+#define SELF_INCLUDING_H
+
+struct first { int a; };
+extern int counter;
+
+#define SECOND_SECTION
+#include "self_including.h"
+#undef SECOND_SECTION
+
+struct third { struct second *second; };
+
+#endif
+#else
+
+struct first;
+extern int counter;
+struct second { struct first *first; };
+
+#endif
@@ -0,0 +1,4 @@
+// Typedefs of typedefs, resolved all the way down to the builtin type.
+typedef int A;
+typedef A B;
+typedef B C;
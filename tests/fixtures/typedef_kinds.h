@@ -0,0 +1,12 @@
+// Typedefs of every kind of type, some through other typedefs.
+struct point {
+    int x;
+    int y;
+};
+enum color { RED, GREEN };
+
+typedef struct point point_t;
+typedef enum color color_t;
+typedef point_t *point_ptr;
+typedef point_ptr handle_t;
+typedef int callback_fn(int);
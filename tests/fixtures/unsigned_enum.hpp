@@ -0,0 +1,5 @@
+enum flags : unsigned long long {
+    LOW = 1,
+    HIGH = 0x8000000000000000,
+    MAX = 0xFFFFFFFFFFFFFFFF,
+};